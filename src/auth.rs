@@ -1,7 +1,8 @@
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use crate::error::SymError;
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -30,67 +31,238 @@ impl AuthHeaders {
         customer_id: &str,
         secret_key: &str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        // Generate timestamp in format: yyyy-MM-dd HH:mm:ss;nanoseconds
-        let now = Utc::now();
-        let date_part = now.format("%Y-%m-%d %H:%M:%S").to_string();
-        let nanos = now.timestamp_subsec_nanos();
-        let sym_date = format!("{};{}", date_part, nanos);
-
-        // Compute MD5 of body if present
-        let (content_md5, body_to_sign) = if let Some(b) = body {
-            let digest = md5::compute(b.as_bytes());
-            let md5_b64 = BASE64.encode(&digest.0);
-            (md5_b64, b)
-        } else {
-            (String::new(), "")
-        };
-
-        // Build string to sign based on whether we have body and/or query
-        let string_to_sign = match (body, query) {
-            (None, None) => {
-                // No body, no query
-                format!(
-                    "{}\n{}\n{}\n{}\n{}\n{}\n",
-                    method, content_md5, secret_key, sym_date, customer_id, resource
-                )
-            }
-            (None, Some(q)) => {
-                // No body, with query
-                format!(
-                    "{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
-                    method, content_md5, secret_key, sym_date, customer_id, resource, q
-                )
-            }
-            (Some(_), None) => {
-                // With body, no query
-                format!(
-                    "{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
-                    method, content_md5, secret_key, sym_date, customer_id, body_to_sign, resource
-                )
-            }
-            (Some(_), Some(q)) => {
-                // With body, with query
-                format!(
-                    "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
-                    method, content_md5, secret_key, sym_date, customer_id, body_to_sign, resource, q
-                )
-            }
-        };
-
-        // Decode the base64 secret key
-        let secret_bytes = BASE64.decode(secret_key)?;
-
-        // Compute HMAC-SHA256
-        let mut mac = HmacSha256::new_from_slice(&secret_bytes)
-            .map_err(|e| format!("Invalid secret key length: {}", e))?;
-        mac.update(string_to_sign.as_bytes());
-        let signature = mac.finalize();
-        let authorization = BASE64.encode(signature.into_bytes());
+        Self::generate_with_date(method, resource, query, body, customer_id, secret_key, &sym_date(Utc::now()))
+    }
+
+    /// Generate authentication headers with a given `Sym-date` value
+    /// Same as `generate`, for reproducible signatures
+    pub fn generate_with_date(
+        method: &str,
+        resource: &str,
+        query: Option<&str>,
+        body: Option<&str>,
+        customer_id: &str,
+        secret_key: &str,
+        sym_date: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let content_md5 = body.map(content_md5).unwrap_or_default();
+        let string_to_sign =
+            rest_string_to_sign(method, &content_md5, secret_key, sym_date, customer_id, body, resource, query);
+
+        Ok(AuthHeaders {
+            authorization: sign(secret_key, &string_to_sign)?,
+            content_md5,
+            sym_date: sym_date.to_string(),
+        })
+    }
+
+    /// Check received REST headers against the request they came with
+    ///
+    /// Fails if the Content-MD5 does not match the body or the signature does not
+    /// match the one computed with `secret_key`.
+    pub fn verify(
+        &self,
+        method: &str,
+        resource: &str,
+        query: Option<&str>,
+        body: Option<&str>,
+        customer_id: &str,
+        secret_key: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let expected_md5 = body.map(content_md5).unwrap_or_default();
+        if self.content_md5 != expected_md5 {
+            return Err(SymError::Auth("Content-MD5 does not match the body".to_string()).into());
+        }
+        let string_to_sign = rest_string_to_sign(
+            method, &self.content_md5, secret_key, &self.sym_date, customer_id, body, resource, query,
+        );
+        verify_signature(secret_key, &string_to_sign, &self.authorization)
+    }
+
+    /// Generate the signature of a WebSocket message
+    ///
+    /// # Arguments
+    /// * `payload` - DataFrame JSON sent after the headers
+    /// * `url` - WebSocket URL, including the scheme (e.g., "ws://host:8080/symetry/ws/learn")
+    /// * `extra_keys` - The project name for learn, the project and model names for predict
+    /// * `customer_id` - Customer ID
+    /// * `secret_key` - Base64-encoded secret key
+    pub fn generate_ws(
+        payload: &str,
+        url: &str,
+        extra_keys: &[String],
+        customer_id: &str,
+        secret_key: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::generate_ws_with_date(payload, url, extra_keys, customer_id, secret_key, &sym_date(Utc::now()))
+    }
+
+    /// Generate the signature of a WebSocket message with a given date
+    /// Same as `generate_ws`, for reproducible signatures
+    pub fn generate_ws_with_date(
+        payload: &str,
+        url: &str,
+        extra_keys: &[String],
+        customer_id: &str,
+        secret_key: &str,
+        sym_date: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let content_md5 = content_md5(payload);
+        let string_to_sign =
+            ws_string_to_sign(&content_md5, secret_key, sym_date, customer_id, payload, url, extra_keys);
 
         Ok(AuthHeaders {
+            authorization: sign(secret_key, &string_to_sign)?,
             content_md5,
-            sym_date,
-            authorization,
+            sym_date: sym_date.to_string(),
         })
     }
+
+    /// Check the headers of a received WebSocket message against its payload
+    pub fn verify_ws(
+        &self,
+        payload: &str,
+        url: &str,
+        extra_keys: &[String],
+        customer_id: &str,
+        secret_key: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.content_md5 != content_md5(payload) {
+            return Err(SymError::Auth("MD5 does not match the payload".to_string()).into());
+        }
+        let string_to_sign = ws_string_to_sign(
+            &self.content_md5, secret_key, &self.sym_date, customer_id, payload, url, extra_keys,
+        );
+        verify_signature(secret_key, &string_to_sign, &self.authorization)
+    }
+}
+
+/// Format a timestamp as a `Sym-date` value: `yyyy-MM-dd HH:mm:ss;nanoseconds`
+pub fn sym_date(now: DateTime<Utc>) -> String {
+    format!("{};{}", now.format("%Y-%m-%d %H:%M:%S"), now.timestamp_subsec_nanos())
+}
+
+/// Check that a secret key decodes as base64 and can key an HMAC-SHA256
+/// The key is checked exactly as it is used for signing, so surrounding whitespace is an error
+/// Returns the length of the decoded key in bytes
+pub fn validate_secret_key(secret_key: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    if secret_key.trim() != secret_key {
+        return Err(SymError::Auth("Secret key has leading or trailing whitespace".to_string()).into());
+    }
+    let secret_bytes = decode_secret_key(secret_key)?;
+    if secret_bytes.is_empty() {
+        return Err(SymError::Auth("Secret key is empty".to_string()).into());
+    }
+    HmacSha256::new_from_slice(&secret_bytes)
+        .map_err(|e| SymError::Auth(format!("Invalid secret key length: {}", e)))?;
+    Ok(secret_bytes.len())
+}
+
+/// Decode the base64 secret key
+fn decode_secret_key(secret_key: &str) -> Result<Vec<u8>, SymError> {
+    BASE64
+        .decode(secret_key)
+        .map_err(|e| SymError::Auth(format!("Secret key is not valid base64: {}", e)))
+}
+
+/// Base64 MD5 digest of a body
+fn content_md5(body: &str) -> String {
+    BASE64.encode(md5::compute(body.as_bytes()).0)
+}
+
+/// Build the REST string to sign
+///
+/// `method\nmd5\nsecret_key\ndate\ncustomer_id\n[body\n]resource\n[query\n]`
+#[allow(clippy::too_many_arguments)]
+fn rest_string_to_sign(
+    method: &str,
+    content_md5: &str,
+    secret_key: &str,
+    sym_date: &str,
+    customer_id: &str,
+    body: Option<&str>,
+    resource: &str,
+    query: Option<&str>,
+) -> String {
+    match (body, query) {
+        (None, None) => {
+            // No body, no query
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n",
+                method, content_md5, secret_key, sym_date, customer_id, resource
+            )
+        }
+        (None, Some(q)) => {
+            // No body, with query
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+                method, content_md5, secret_key, sym_date, customer_id, resource, q
+            )
+        }
+        (Some(b), None) => {
+            // With body, no query
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+                method, content_md5, secret_key, sym_date, customer_id, b, resource
+            )
+        }
+        (Some(b), Some(q)) => {
+            // With body, with query
+            format!(
+                "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n",
+                method, content_md5, secret_key, sym_date, customer_id, b, resource, q
+            )
+        }
+    }
+}
+
+/// Build the WebSocket string to sign (different format than REST!)
+///
+/// `md5\nsecret_key\ndate\ncustomer_id\npayload\nurl\nextra_key\n...`
+fn ws_string_to_sign(
+    content_md5: &str,
+    secret_key: &str,
+    sym_date: &str,
+    customer_id: &str,
+    payload: &str,
+    url: &str,
+    extra_keys: &[String],
+) -> String {
+    let mut string_to_sign = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n",
+        content_md5, secret_key, sym_date, customer_id, payload, url
+    );
+    for key in extra_keys {
+        string_to_sign.push_str(key);
+        string_to_sign.push('\n');
+    }
+    string_to_sign
+}
+
+/// Create the HMAC-SHA256 of a string keyed with the decoded secret key
+fn mac(secret_key: &str, string_to_sign: &str) -> Result<HmacSha256, Box<dyn std::error::Error>> {
+    let secret_bytes = decode_secret_key(secret_key)?;
+    let mut mac = HmacSha256::new_from_slice(&secret_bytes)
+        .map_err(|e| SymError::Auth(format!("Invalid secret key length: {}", e)))?;
+    mac.update(string_to_sign.as_bytes());
+    Ok(mac)
+}
+
+/// Compute the base64 signature of a string
+fn sign(secret_key: &str, string_to_sign: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(BASE64.encode(mac(secret_key, string_to_sign)?.finalize().into_bytes()))
+}
+
+/// Compare a base64 signature with the expected one in constant time
+fn verify_signature(
+    secret_key: &str,
+    string_to_sign: &str,
+    authorization: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let signature = BASE64
+        .decode(authorization)
+        .map_err(|_| SymError::Auth("Authorization is not valid base64".to_string()))?;
+    mac(secret_key, string_to_sign)?
+        .verify_slice(&signature)
+        .map_err(|_| SymError::Auth("Signature does not match".to_string()).into())
 }
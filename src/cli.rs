@@ -1,4 +1,8 @@
 use clap::{Args, Parser, Subcommand};
+use crate::output::OutputFormat;
+use sym_rest_rc::config::Profile;
+use sym_rest_rc::data::CsvOptions;
+use sym_rest_rc::transport::TransportKind;
 
 #[derive(Parser)]
 #[command(name = "sym-rest-rc")]
@@ -8,14 +12,81 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub config: Option<String>,
 
+    /// Name of the connection profile to use (overrides SML_PROFILE)
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    /// Host of the server (overrides SML_HOST and `host`)
+    #[arg(long, global = true)]
+    pub host: Option<String>,
+
+    /// Port of the server (overrides SML_PORT and `port`)
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+
+    /// User (customer ID) to sign requests with (overrides SML_USER and `user`)
+    #[arg(long, global = true)]
+    pub user: Option<String>,
+
+    /// Print the first signed request as a curl command (or WebSocket message) instead of sending it
+    #[arg(long, global = true, default_value_t = false)]
+    pub dry_run: bool,
+
+    /// With --dry-run, show the signature instead of redacting it
+    #[arg(long, global = true, default_value_t = false, requires = "dry_run")]
+    pub show_secret: bool,
+
+    /// Format of the command result; non-text formats send progress messages to stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Number of retries after a network error or a 502/503/504 response (overrides `retries`)
+    #[arg(long, global = true)]
+    pub retries: Option<u32>,
+
+    /// Also retry calls that are not idempotent, such as learn (overrides `retry_non_idempotent`)
+    #[arg(long, global = true, default_value_t = false)]
+    pub retry_non_idempotent: bool,
+
+    /// Seconds allowed to connect to the server, 0 for no limit (overrides `connect_timeout`)
+    #[arg(long, global = true)]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds allowed for a whole REST request, 0 for no limit (overrides `request_timeout`)
+    #[arg(long, global = true)]
+    pub request_timeout: Option<u64>,
+
+    /// Seconds allowed without a message on a WebSocket, 0 for no limit (overrides `ws_idle_timeout`)
+    #[arg(long, global = true)]
+    pub ws_idle_timeout: Option<u64>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+impl Cli {
+    /// Settings given as flags, applied on top of the configuration file
+    pub fn overrides(&self) -> Profile {
+        Profile {
+            host: self.host.clone(),
+            port: self.port,
+            user: self.user.clone(),
+            retries: self.retries,
+            retry_non_idempotent: self.retry_non_idempotent.then_some(true),
+            connect_timeout: self.connect_timeout,
+            request_timeout: self.request_timeout,
+            ws_idle_timeout: self.ws_idle_timeout,
+            ..Profile::default()
+        }
+    }
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Display the currently loaded configuration
+    /// Display the currently loaded configuration and the available profiles
     Config,
+    /// Check the configuration, connectivity and credentials, with hints to fix failures
+    Doctor,
     /// Project management commands
     Project(ProjectCommand),
     /// Model management commands
@@ -26,6 +97,10 @@ pub enum Commands {
     Learn(LearnArgs),
     /// Check the status of an asynchronous job
     Job(JobArgs),
+    /// Run the steps of a pipeline file in order, stopping at the first failure
+    Run(RunArgs),
+    /// Send a signed request to any REST endpoint and print the response
+    Raw(RawArgs),
 }
 
 #[derive(Args)]
@@ -116,12 +191,25 @@ pub struct LearnArgs {
     /// Path to the dataset file
     #[arg(long)]
     pub file: String,
-    /// Data types (comma-separated, e.g., "C,C,C,B,B")
-    #[arg(long)]
-    pub types: String,
-    /// Use WebSocket for communication
+    #[command(flatten)]
+    pub csv: CsvArgs,
+    /// Data types (comma-separated, e.g., "C,C,C,B,B"), or "auto" to infer them (default)
+    #[arg(long)]
+    pub types: Option<String>,
+    #[command(flatten)]
+    pub transport: TransportArgs,
+    /// Stream the file in batches of N rows instead of sending it all at once
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub batch_rows: Option<u64>,
+    /// Over WebSocket, number of batches sent before waiting for an acknowledgement
+    #[arg(long, default_value_t = sym_rest_rc::ws::DEFAULT_WINDOW as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub ws_window: u64,
+    /// Wait for the asynchronous job to finish
     #[arg(long, default_value_t = false)]
-    pub use_ws: bool,
+    pub wait: bool,
+    /// Maximum time to wait for the job, in seconds
+    #[arg(long, default_value_t = 600)]
+    pub wait_timeout: u64,
 }
 
 #[derive(Args)]
@@ -150,6 +238,12 @@ pub struct BuildArgs {
     /// Model parameters (key=value pairs, comma-separated)
     #[arg(long)]
     pub params: Option<String>,
+    /// Wait for the asynchronous job to finish
+    #[arg(long, default_value_t = false)]
+    pub wait: bool,
+    /// Maximum time to wait for the job, in seconds
+    #[arg(long, default_value_t = 600)]
+    pub wait_timeout: u64,
 }
 
 #[derive(Args)]
@@ -186,6 +280,11 @@ pub struct AutoselectArgs {
     /// Path to validation data file
     #[arg(long)]
     pub val_file: Option<String>,
+    /// Data types for --val-file (comma-separated, e.g., "C,C,C,B,B"), or "auto" to infer them (default)
+    #[arg(long)]
+    pub types: Option<String>,
+    #[command(flatten)]
+    pub csv: CsvArgs,
     /// Validation dataframe in JSON format
     #[arg(long)]
     pub val_df: Option<String>,
@@ -204,6 +303,12 @@ pub struct AutoselectArgs {
     /// Model parameters (key=value pairs, comma-separated)
     #[arg(long)]
     pub params: Option<String>,
+    /// Wait for the asynchronous job to finish
+    #[arg(long, default_value_t = false)]
+    pub wait: bool,
+    /// Maximum time to wait for the job, in seconds
+    #[arg(long, default_value_t = 600)]
+    pub wait_timeout: u64,
 }
 
 #[derive(Args)]
@@ -220,9 +325,22 @@ pub struct PredictArgs {
     /// Path to the data file
     #[arg(long)]
     pub file: Option<String>,
-    /// Use WebSocket for communication
-    #[arg(long, default_value_t = false)]
-    pub use_ws: bool,
+    /// Data types for --file (comma-separated, e.g., "C,C,C,B,B"), or "auto" to infer them (default)
+    #[arg(long)]
+    pub types: Option<String>,
+    #[command(flatten)]
+    pub csv: CsvArgs,
+    /// Write the input rows with the predicted values appended to a .csv or .jsonl file
+    #[arg(long)]
+    pub out: Option<String>,
+    #[command(flatten)]
+    pub transport: TransportArgs,
+    /// With --file, stream the file in batches of N rows instead of sending it all at once
+    #[arg(long, requires = "file", value_parser = clap::value_parser!(u64).range(1..))]
+    pub batch_rows: Option<u64>,
+    /// Over WebSocket, number of batches sent before waiting for the predictions
+    #[arg(long, default_value_t = sym_rest_rc::ws::DEFAULT_WINDOW as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub ws_window: u64,
 }
 
 #[derive(Args)]
@@ -230,6 +348,32 @@ pub struct JobArgs {
     /// Job ID to check status
     #[arg(long)]
     pub id: String,
+    /// Poll the job until it is done or failed
+    #[arg(long, default_value_t = false)]
+    pub watch: bool,
+    /// Maximum time to watch the job, in seconds
+    #[arg(long, default_value_t = 600)]
+    pub wait_timeout: u64,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Path to the pipeline file (TOML)
+    pub file: String,
+}
+
+#[derive(Args)]
+pub struct RawArgs {
+    /// HTTP method (GET, POST, PUT, DELETE...)
+    pub method: String,
+    /// Resource path, relative to /symetry/rest/{user} (e.g. "projects/p1/info") unless it starts with /symetry/
+    pub resource: String,
+    /// Query parameter as key=value, sent and signed as given; may be repeated
+    #[arg(long)]
+    pub query: Vec<String>,
+    /// JSON request body, or @path to read it from a file
+    #[arg(long)]
+    pub body: Option<String>,
 }
 
 #[derive(Args)]
@@ -254,3 +398,43 @@ pub struct EvtwrapperDeleteArgs {
     #[arg(long)]
     pub model: String,
 }
+
+#[derive(Args)]
+pub struct CsvArgs {
+    /// Field delimiter (a single character, or "tab")
+    #[arg(long, default_value = ",")]
+    pub delimiter: String,
+    /// The data file has no header line (requires --columns)
+    #[arg(long, default_value_t = false, requires = "columns")]
+    pub no_header: bool,
+    /// Attribute names for a file without header (comma-separated)
+    #[arg(long, requires = "no_header")]
+    pub columns: Option<String>,
+}
+
+impl CsvArgs {
+    /// Convert to the options used by the data loader
+    pub fn options(&self) -> Result<CsvOptions, Box<dyn std::error::Error>> {
+        CsvOptions::new(&self.delimiter, self.no_header, self.columns.as_deref())
+    }
+}
+
+#[derive(Args)]
+pub struct TransportArgs {
+    /// Send the data over REST or WebSocket: rest or ws (default from use_ws_for_learn / use_ws_for_predit)
+    #[arg(long)]
+    pub transport: Option<TransportKind>,
+    /// Same as --transport ws
+    #[arg(long, default_value_t = false, conflicts_with = "transport")]
+    pub use_ws: bool,
+}
+
+impl TransportArgs {
+    /// Transport to use, `use_ws` being the configured default
+    pub fn kind(&self, use_ws: bool) -> TransportKind {
+        if self.use_ws {
+            return TransportKind::Ws;
+        }
+        self.transport.unwrap_or(TransportKind::from_use_ws(use_ws))
+    }
+}
@@ -0,0 +1,688 @@
+use crate::auth::AuthHeaders;
+use crate::common::{DataFrame, MLContext};
+use crate::config;
+use crate::connection::{describe_timeout, Timeouts, TlsSettings};
+use crate::error::SymError;
+use crate::responses::{JobState, JobStatus};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::future::{Future, IntoFuture};
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// API version sent in the `sym-version` header of every request
+pub const SYM_VERSION: &str = "6.3";
+
+/// Print requests instead of sending them, see `config::Config::dry_run`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DryRun {
+    /// Show the signature instead of redacting it
+    pub show_secret: bool,
+}
+
+/// A signed REST request, with the URL and headers exactly as they are sent
+#[derive(Debug, Clone)]
+pub struct PreparedRequest {
+    pub method: Method,
+    pub url: String,
+    /// Headers, in the order they are sent
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Option<String>,
+}
+
+impl PreparedRequest {
+    /// Format the request as an equivalent curl command
+    /// The `Authorization` header is redacted unless `show_secret` is set
+    pub fn to_curl(&self, show_secret: bool) -> String {
+        let mut curl = format!("curl -X {} {}", self.method, shell_quote(&self.url));
+        for (name, value) in &self.headers {
+            let header = match value.as_str() {
+                // curl drops headers given as "Name:", and sends "Name;" as an empty header
+                "" => format!("{};", name),
+                _ if *name == "Authorization" && !show_secret => format!("{}: {}", name, config::redact(value)),
+                _ => format!("{}: {}", name, value),
+            };
+            curl.push_str(&format!(" \\\n  -H {}", shell_quote(&header)));
+        }
+        if let Some(body) = &self.body {
+            curl.push_str(&format!(" \\\n  --data-raw {}", shell_quote(body)));
+        }
+        curl
+    }
+}
+
+/// A request to the server, signed and sent when awaited
+///
+/// Returned by the API methods of `SymClient`. `prepare` signs it without
+/// sending it, which is how dry runs print requests.
+pub struct Call<'a> {
+    client: &'a SymClient,
+    method: Method,
+    resource: String,
+    query: Option<String>,
+    /// Request body, or the error encoding it
+    body: Result<Option<String>, String>,
+    /// Safe to retry; by default every method but POST and PATCH
+    idempotent: bool,
+}
+
+impl<'a> Call<'a> {
+    fn new(client: &'a SymClient, method: Method, resource: String, query: Option<String>, body: Option<String>) -> Self {
+        let idempotent = !matches!(method, Method::POST | Method::PATCH);
+        Call {
+            client,
+            method,
+            resource,
+            query,
+            body: Ok(body),
+            idempotent,
+        }
+    }
+
+    /// Send `body` encoded as JSON
+    fn json<T: Serialize>(mut self, body: &T) -> Self {
+        self.body = serde_json::to_string(body).map(Some).map_err(|e| e.to_string());
+        self
+    }
+
+    /// Retry transient failures even if the method is not idempotent
+    fn idempotent(mut self) -> Self {
+        self.idempotent = true;
+        self
+    }
+
+    fn body(&self) -> Result<Option<String>, SymError> {
+        self.body
+            .clone()
+            .map_err(|e| SymError::Other(format!("Failed to encode the request body: {}", e)))
+    }
+
+    /// Sign the request without sending it
+    pub fn prepare(&self) -> Result<PreparedRequest, Box<dyn std::error::Error>> {
+        self.client.prepare(self.method.clone(), &self.resource, self.query.as_deref(), self.body()?)
+    }
+
+    /// Send the request and collect the response, retrying transient failures
+    /// according to the retry policy of the client
+    pub async fn send(self) -> Result<SymResponse, Box<dyn std::error::Error>> {
+        let body = self.body()?;
+        self.client
+            .send_with_retry(self.method, &self.resource, self.query.as_deref(), body, self.idempotent)
+            .await
+    }
+}
+
+impl<'a> IntoFuture for Call<'a> {
+    type Output = Result<SymResponse, Box<dyn std::error::Error>>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.send())
+    }
+}
+
+/// Quote a value for a POSIX shell
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Request body for the autoSelect endpoint
+#[derive(Serialize, Debug)]
+struct AutoSelectRequest<'a> {
+    dataframe: &'a DataFrame,
+    mlcontext: &'a MLContext,
+}
+
+/// Response returned by the SymetryML REST server
+#[derive(Debug)]
+pub struct SymResponse {
+    pub status: StatusCode,
+    /// Value of the `sym-job-id` header for asynchronous operations
+    pub job_id: Option<String>,
+    /// Value of the `Date` header, the server's clock
+    pub date: Option<String>,
+    pub body: String,
+}
+
+impl SymResponse {
+    /// Check if the server answered with a 2xx status
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+
+    /// Get the body as JSON, or as a JSON string if it is not valid JSON
+    pub fn body_value(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body)
+            .unwrap_or_else(|_| serde_json::Value::String(self.body.clone()))
+    }
+}
+
+/// Polling schedule used by `SymClient::wait_for_job`
+///
+/// The interval starts at `initial_interval` and doubles after every poll up to
+/// `max_interval`. Polling gives up once `timeout` has elapsed.
+#[derive(Debug, Clone)]
+pub struct PollOptions {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl PollOptions {
+    /// Default schedule with a custom overall timeout
+    pub fn with_timeout(timeout: Duration) -> Self {
+        PollOptions {
+            timeout,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        PollOptions {
+            initial_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(15),
+            timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Retry policy for transient failures
+///
+/// Network errors and responses with one of `retry_statuses` are retried up to
+/// `max_retries` times. The delay starts at `initial_backoff` and doubles after
+/// every retry up to `max_backoff`, with random jitter of up to half the delay.
+/// Calls that are not idempotent (POST, except predict) are only retried when
+/// `retry_non_idempotent` is set, so data is never learned twice by accident.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub retry_statuses: Vec<u16>,
+    pub retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Policy that never retries
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        // Jitter between 50% and 100% of the delay, so clients do not retry in lockstep.
+        // Every RandomState has its own random keys, which makes the hash a random number
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(retry);
+        let fraction = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+        base.mul_f64(0.5 + fraction / 2.0)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            retry_statuses: vec![502, 503, 504],
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+/// Async client for the SymetryML REST API
+///
+/// Every request is signed with `AuthHeaders::generate` using the customer ID
+/// and secret key the client was created with.
+pub struct SymClient {
+    host: String,
+    port: u16,
+    customer_id: String,
+    secret_key: String,
+    tls: TlsSettings,
+    timeouts: Timeouts,
+    retry: RetryPolicy,
+    http: reqwest::Client,
+}
+
+impl SymClient {
+    /// Create a client for the given server and credentials
+    ///
+    /// # Arguments
+    /// * `host` - Server host name
+    /// * `port` - Server port
+    /// * `customer_id` - Customer ID (the `user` in the config file)
+    /// * `secret_key` - Base64-encoded secret key
+    pub fn new(
+        host: impl Into<String>,
+        port: u16,
+        customer_id: impl Into<String>,
+        secret_key: impl Into<String>,
+    ) -> Self {
+        let tls = TlsSettings::default();
+        let timeouts = Timeouts::default();
+        // Building a client without certificate files only fails if the TLS backend cannot start
+        let http = tls.http_client(&timeouts).unwrap_or_else(|_| reqwest::Client::new());
+        SymClient {
+            host: host.into(),
+            port,
+            customer_id: customer_id.into(),
+            secret_key: secret_key.into(),
+            tls,
+            timeouts,
+            retry: RetryPolicy::none(),
+            http,
+        }
+    }
+
+    /// Use TLS (https) with the given settings
+    pub fn with_tls(mut self, tls: TlsSettings) -> Result<Self, Box<dyn std::error::Error>> {
+        self.http = tls.http_client(&self.timeouts)?;
+        self.tls = tls;
+        Ok(self)
+    }
+
+    /// Use the given connect and request timeouts
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Result<Self, Box<dyn std::error::Error>> {
+        self.http = self.tls.http_client(&timeouts)?;
+        self.timeouts = timeouts;
+        Ok(self)
+    }
+
+    /// Retry transient failures with the given policy
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Create a client from the loaded configuration
+    pub fn from_config() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::new(
+            config::Config::host(),
+            config::Config::port(),
+            config::Config::user(),
+            config::Config::secretkey(),
+        )
+        .with_timeouts(config::Config::timeouts().clone())?
+        .with_tls(config::Config::tls().clone())
+        .map(|client| client.with_retry(config::Config::retry().clone()))
+    }
+
+    /// Get the customer ID used to sign requests
+    pub fn customer_id(&self) -> &str {
+        &self.customer_id
+    }
+
+    /// Build a resource path under `/symetry/rest/{cid}`
+    pub fn resource(&self, path: &str) -> String {
+        format!("/symetry/rest/{}{}", self.customer_id, path)
+    }
+
+    /// Build a signed request to any resource, sent when awaited
+    ///
+    /// Transient failures are retried according to the retry policy. POST requests
+    /// are treated as not idempotent.
+    ///
+    /// # Arguments
+    /// * `method` - HTTP method
+    /// * `resource` - API resource path (e.g., "/symetry/rest/c1/projects")
+    /// * `query` - Query string, signed as-is
+    /// * `body` - Request body as JSON string
+    pub fn send(&self, method: Method, resource: &str, query: Option<&str>, body: Option<String>) -> Call<'_> {
+        Call::new(self, method, resource.to_string(), query.map(str::to_string), body)
+    }
+
+    /// Send a request, retrying transient failures
+    async fn send_with_retry(
+        &self,
+        method: Method,
+        resource: &str,
+        query: Option<&str>,
+        body: Option<String>,
+        idempotent: bool,
+    ) -> Result<SymResponse, Box<dyn std::error::Error>> {
+        let max_retries = if idempotent || self.retry.retry_non_idempotent {
+            self.retry.max_retries
+        } else {
+            0
+        };
+
+        let mut retry = 0;
+        loop {
+            // Every attempt is signed again, since Sym-date is part of the signature
+            let result = self.send_once(method.clone(), resource, query, body.clone()).await;
+            let transient = match &result {
+                Ok(response) => self.retry.retry_statuses.contains(&response.status.as_u16()),
+                Err(e) => is_transient(e.as_ref()),
+            };
+
+            if !transient || retry >= max_retries {
+                return match result {
+                    Err(e) => Err(match self.describe_error(e, &method, resource) {
+                        SymError::Network(m) if retry > 0 => {
+                            SymError::Network(format!("{} (after {} retries)", m, retry))
+                        }
+                        e => e,
+                    }
+                    .into()),
+                    ok => ok,
+                };
+            }
+
+            retry += 1;
+            tokio::time::sleep(self.retry.backoff(retry)).await;
+        }
+    }
+
+    /// Classify a failed request, naming the timeout that fired if the error is a timeout
+    fn describe_error(
+        &self,
+        error: Box<dyn std::error::Error>,
+        method: &Method,
+        resource: &str,
+    ) -> SymError {
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_timeout() && e.is_connect() => SymError::Network(format!(
+                "{} elapsed while connecting to {}:{}",
+                describe_timeout("connect_timeout", self.timeouts.connect),
+                self.host,
+                self.port
+            )),
+            Some(e) if e.is_timeout() => SymError::Network(format!(
+                "{} elapsed waiting for the response to {} {}",
+                describe_timeout("request_timeout", self.timeouts.request),
+                method,
+                resource
+            )),
+            _ => SymError::classify(error),
+        }
+    }
+
+    /// Sign a request without sending it
+    ///
+    /// Every call signs again with the current time, since `Sym-date` is part of the signature.
+    pub fn prepare(
+        &self,
+        method: Method,
+        resource: &str,
+        query: Option<&str>,
+        body: Option<String>,
+    ) -> Result<PreparedRequest, Box<dyn std::error::Error>> {
+        // Build the full URL
+        let scheme = self.tls.http_scheme();
+        let url = match query {
+            Some(q) => format!("{}://{}:{}{}?{}", scheme, self.host, self.port, resource, q),
+            None => format!("{}://{}:{}{}", scheme, self.host, self.port, resource),
+        };
+
+        // Generate authentication headers
+        let auth = AuthHeaders::generate(
+            method.as_str(),
+            resource,
+            query,
+            body.as_deref(),
+            &self.customer_id,
+            &self.secret_key,
+        )?;
+
+        let mut headers = vec![
+            ("Content-MD5", auth.content_md5),
+            ("Sym-date", auth.sym_date),
+            ("Customer-ID", self.customer_id.clone()),
+            ("Authorization", auth.authorization),
+            ("sym-version", SYM_VERSION.to_string()),
+        ];
+        if body.is_some() {
+            headers.push(("Content-Type", "application/json".to_string()));
+        }
+
+        Ok(PreparedRequest { method, url, headers, body })
+    }
+
+    /// Send a signed request once
+    async fn send_once(
+        &self,
+        method: Method,
+        resource: &str,
+        query: Option<&str>,
+        body: Option<String>,
+    ) -> Result<SymResponse, Box<dyn std::error::Error>> {
+        let prepared = self.prepare(method, resource, query, body)?;
+
+        let mut request = self.http.request(prepared.method, &prepared.url);
+        for (name, value) in prepared.headers {
+            request = request.header(name, value);
+        }
+        if let Some(b) = prepared.body {
+            request = request.body(b);
+        }
+
+        let response = request.send().await?;
+
+        let status = response.status();
+
+        // Extract the sym-job-id header if present
+        let job_id = response.headers()
+            .get("sym-job-id")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string());
+        let date = response.headers()
+            .get(reqwest::header::DATE)
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.to_string());
+
+        let body = response.text().await?;
+
+        Ok(SymResponse { status, job_id, date, body })
+    }
+
+    /// Create a project
+    ///
+    /// `sml_project_autosave` defaults to `true` unless set in `params`.
+    pub fn create_project(
+        &self,
+        name: &str,
+        project_type: &str,
+        params: &HashMap<String, String>,
+        enable_histogram: bool,
+    ) -> Call<'_> {
+        let resource = self.resource("/projects");
+        let query = format!(
+            "pid={}&persist=true&type={}&enableHistogram={}",
+            name, project_type, enable_histogram
+        );
+
+        let mut body_params = params.clone();
+        body_params
+            .entry("sml_project_autosave".to_string())
+            .or_insert_with(|| "true".to_string());
+        self.send(Method::POST, &resource, Some(&query), None).json(&body_params)
+    }
+
+    /// List the projects of the customer
+    pub fn list_projects(&self) -> Call<'_> {
+        let resource = self.resource("/projects");
+        self.send(Method::GET, &resource, None, None)
+    }
+
+    /// Delete a project
+    pub fn delete_project(&self, project: &str) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}", project));
+        self.send(Method::DELETE, &resource, None, None)
+    }
+
+    /// Get project information
+    pub fn project_info(&self, project: &str) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/info", project));
+        self.send(Method::GET, &resource, None, None)
+    }
+
+    /// Push a DataFrame to a project
+    pub fn learn(
+        &self,
+        project: &str,
+        dataframe: &DataFrame,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/learn", project));
+        self.send(Method::POST, &resource, None, None).json(dataframe)
+    }
+
+    /// Build a model (asynchronous - the response carries a job ID)
+    pub fn build(
+        &self,
+        project: &str,
+        model: &str,
+        algo: &str,
+        ml_context: &MLContext,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/build", project));
+        let query = format!("algo={}&modelid={}", algo, model);
+        self.send(Method::POST, &resource, Some(&query), None).json(ml_context)
+    }
+
+    /// Auto-select the best algorithm for a model (asynchronous - the response carries a job ID)
+    pub fn autoselect(
+        &self,
+        project: &str,
+        model: &str,
+        task: &str,
+        validation: &DataFrame,
+        ml_context: &MLContext,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/autoSelect", project));
+        let query = format!("task={}&modelid={}", task, model);
+        self.send(Method::POST, &resource, Some(&query), None).json(&AutoSelectRequest {
+            dataframe: validation,
+            mlcontext: ml_context,
+        })
+    }
+
+    /// Make predictions with a model
+    pub fn predict(
+        &self,
+        project: &str,
+        model: &str,
+        dataframe: &DataFrame,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/predict/{}", project, model));
+        // Predictions do not change the server state, so they are safe to retry
+        self.send(Method::POST, &resource, None, None).json(dataframe).idempotent()
+    }
+
+    /// Get the status of an asynchronous job
+    pub fn job_status(&self, job_id: &str) -> Call<'_> {
+        let resource = self.resource(&format!("/jobs/{}", job_id));
+        self.send(Method::GET, &resource, None, None)
+    }
+
+    /// Poll an asynchronous job until it is done or failed
+    ///
+    /// `on_change` is called with the first status and on every state transition.
+    /// Returns the final status, or an error if `options.timeout` elapses first.
+    pub async fn wait_for_job<F>(
+        &self,
+        job_id: &str,
+        options: &PollOptions,
+        mut on_change: F,
+    ) -> Result<JobStatus, Box<dyn std::error::Error>>
+    where
+        F: FnMut(&JobStatus),
+    {
+        let started = Instant::now();
+        let mut interval = options.initial_interval;
+        let mut last_state: Option<JobState> = None;
+
+        loop {
+            let response = self.job_status(job_id).await?;
+            let status = JobStatus::from_response(job_id, &response)?;
+
+            if last_state.as_ref() != Some(&status.state) {
+                on_change(&status);
+                last_state = Some(status.state.clone());
+            }
+
+            if status.state.is_terminal() {
+                return Ok(status);
+            }
+
+            let elapsed = started.elapsed();
+            if elapsed >= options.timeout {
+                return Err(SymError::Job {
+                    job_id: job_id.to_string(),
+                    message: format!(
+                        "Timed out after {}s waiting for job {} (last state: {})",
+                        options.timeout.as_secs(),
+                        job_id,
+                        status.state
+                    ),
+                }
+                .into());
+            }
+
+            tokio::time::sleep(interval.min(options.timeout - elapsed)).await;
+            interval = (interval * 2).min(options.max_interval);
+        }
+    }
+
+    /// Delete a model from a project
+    pub fn delete_model(
+        &self,
+        project: &str,
+        model: &str,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/models/{}", project, model));
+        self.send(Method::DELETE, &resource, None, None)
+    }
+
+    /// Create an EVT wrapper for a model
+    pub fn create_evtwrapper(
+        &self,
+        project: &str,
+        model: &str,
+        params: &HashMap<String, String>,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/{}/evtwrapper", project, model));
+        self.send(Method::POST, &resource, None, None).json(params)
+    }
+
+    /// Delete the EVT wrapper of a model
+    pub fn delete_evtwrapper(
+        &self,
+        project: &str,
+        model: &str,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/{}/evtwrapper", project, model));
+        self.send(Method::DELETE, &resource, None, None)
+    }
+
+    /// Get model information
+    pub fn model_info(
+        &self,
+        project: &str,
+        model: &str,
+    ) -> Call<'_> {
+        let resource = self.resource(&format!("/projects/{}/models/{}", project, model));
+        self.send(Method::GET, &resource, None, None)
+    }
+}
+
+/// Check if an error is a network failure worth retrying
+fn is_transient(error: &(dyn std::error::Error + 'static)) -> bool {
+    match error.downcast_ref::<reqwest::Error>() {
+        Some(e) => e.is_connect() || e.is_timeout() || e.is_request() || e.is_body(),
+        None => false,
+    }
+}
@@ -0,0 +1,292 @@
+use crate::output;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+use sym_rest_rc::auth::validate_secret_key;
+use sym_rest_rc::client::RetryPolicy;
+use sym_rest_rc::config::{self, ConfigError};
+use sym_rest_rc::connection::{connect_ws, describe_timeout};
+use sym_rest_rc::{SymClient, SymError, SymResponse};
+use tokio::net::TcpStream;
+
+/// Largest difference between the local clock and the server's `Date` header
+/// that is not reported
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+/// Result of one check
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+    /// How to fix a failed check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.status {
+            CheckStatus::Pass => "PASS",
+            CheckStatus::Fail => "FAIL",
+            CheckStatus::Skip => "SKIP",
+        };
+        write!(f, "[{}] {:<16} {}", status, self.name, self.detail)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n       hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks run so far, printed as they complete
+#[derive(Default, Serialize)]
+struct Report {
+    checks: Vec<Check>,
+}
+
+impl Report {
+    fn add(&mut self, name: &'static str, status: CheckStatus, detail: String, hint: Option<String>) {
+        let check = Check { name, status, detail, hint };
+        msg!("{}", check);
+        self.checks.push(check);
+    }
+
+    fn pass(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.add(name, CheckStatus::Pass, detail.into(), None);
+    }
+
+    fn fail(&mut self, name: &'static str, detail: impl Into<String>, hint: impl Into<String>) {
+        self.add(name, CheckStatus::Fail, detail.into(), Some(hint.into()));
+    }
+
+    fn skip(&mut self, name: &'static str, detail: impl Into<String>) {
+        self.add(name, CheckStatus::Skip, detail.into(), None);
+    }
+
+    fn failed(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail).count()
+    }
+}
+
+pub async fn handle_doctor(
+    config_result: Result<String, ConfigError>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut report = Report::default();
+
+    // Configuration file
+    match &config_result {
+        Ok(path) => report.pass("config", format!("loaded from {}", path)),
+        Err(e) => report.fail(
+            "config",
+            e.to_string(),
+            "pass --config, set SML_CONFIG_FILE or SML_HOST/SML_PORT/SML_USER, or fix the file (see etc/rc.conf for an example)",
+        ),
+    }
+
+    if config_result.is_ok() {
+        run_checks(&mut report).await;
+    }
+
+    let failed = report.failed();
+    output::emit(&serde_json::json!({
+        "passed": failed == 0,
+        "checks": report.checks,
+    }))?;
+
+    if let Err(e) = config_result {
+        return Err(SymError::Config(e).into());
+    }
+    if let Some(check) = report.checks.iter().find(|c| c.status == CheckStatus::Fail) {
+        let message = format!("{} of {} checks failed", failed, report.checks.len());
+        return Err(check_error(check.name, message).into());
+    }
+    msg!("All checks passed");
+    Ok(())
+}
+
+/// Error for a failed check, of the kind a command failing the same way reports
+/// Checks that fail after the first one are usually caused by it, so only the first one counts
+fn check_error(name: &str, message: String) -> SymError {
+    match name {
+        "host reachable" => SymError::Network(message),
+        "websocket" => SymError::WebSocket(message),
+        // A wrong key or a skewed clock makes the server reject the signature
+        _ => SymError::Auth(message),
+    }
+}
+
+/// Checks that need a loaded configuration
+async fn run_checks(report: &mut Report) {
+    let host = config::Config::host();
+    let port = config::Config::port();
+    let timeouts = config::Config::timeouts();
+
+    // Secret key
+    let secret_ok = match validate_secret_key(&config::Config::secretkey()) {
+        Ok(len) => {
+            report.pass(
+                "secret key",
+                format!("{}-byte key from {}", len, config::Config::secretkey_source()),
+            );
+            true
+        }
+        Err(e) => {
+            report.fail(
+                "secret key",
+                format!("{} (from {})", e, config::Config::secretkey_source()),
+                "set secretkey, secretkey_file, secretkey_command or SML_SK to the base64 key of your user",
+            );
+            false
+        }
+    };
+
+    // A dry run sends nothing, so only the local checks are run
+    if config::Config::dry_run().is_some() {
+        for name in ["host reachable", "authentication", "websocket", "clock"] {
+            report.skip(name, "dry run, nothing is sent");
+        }
+        return;
+    }
+
+    // TCP connection
+    let connect = TcpStream::connect((host, port));
+    let reachable = match timeouts.connect {
+        Some(t) => tokio::time::timeout(t, connect)
+            .await
+            .unwrap_or_else(|_| Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("{} elapsed", describe_timeout("connect_timeout", timeouts.connect)),
+            ))),
+        None => connect.await,
+    };
+    let reachable = match reachable {
+        Ok(_) => {
+            report.pass("host reachable", format!("{}:{}", host, port));
+            true
+        }
+        Err(e) => {
+            report.fail(
+                "host reachable",
+                format!("{}:{}: {}", host, port, e),
+                "check host and port, that the server is running, and that no firewall blocks the port",
+            );
+            false
+        }
+    };
+
+    // Signed REST request
+    let response = if !reachable {
+        report.skip("authentication", "host not reachable");
+        None
+    } else if !secret_ok {
+        report.skip("authentication", "no valid secret key");
+        None
+    } else {
+        check_authentication(report).await
+    };
+
+    // WebSocket handshake
+    if reachable {
+        let tls = config::Config::tls();
+        let url = format!("{}://{}:{}/symetry/ws/learn", tls.ws_scheme(), host, port);
+        match connect_ws(&url, tls, timeouts).await {
+            Ok(mut ws) => {
+                let _ = ws.close(None).await;
+                report.pass("websocket", url);
+            }
+            Err(e) => report.fail(
+                "websocket",
+                format!("{}: {}", url, e),
+                "check that proxies allow WebSocket upgrades, or set use_ws_for_learn and use_ws_for_predit to false",
+            ),
+        }
+    } else {
+        report.skip("websocket", "host not reachable");
+    }
+
+    // Clock skew
+    match response.as_ref().map(|r| r.date.as_deref()) {
+        None => report.skip("clock", "no response from the server"),
+        Some(None) => report.skip("clock", "the server did not send a Date header"),
+        Some(Some(date)) => check_clock(report, date),
+    }
+}
+
+/// Send a signed GET and check that the server accepts the signature
+/// Returns the response, if any, for the clock check
+async fn check_authentication(report: &mut Report) -> Option<SymResponse> {
+    // A single attempt, so a failing server is reported right away
+    let client = match SymClient::from_config() {
+        Ok(client) => client.with_retry(RetryPolicy::none()),
+        Err(e) => {
+            report.fail("authentication", e.to_string(), "check the tls, ca_cert, client_cert and client_key settings");
+            return None;
+        }
+    };
+
+    match client.list_projects().await {
+        Ok(response) => {
+            let detail = format!("GET /symetry/rest/{}/projects: {}", client.customer_id(), response.status);
+            match response.status.as_u16() {
+                _ if response.is_success() => report.pass("authentication", detail),
+                401 | 403 => report.fail(
+                    "authentication",
+                    detail,
+                    "the server rejected the signature: check user and the secret key, and that the local clock is right",
+                ),
+                _ => report.fail(
+                    "authentication",
+                    detail,
+                    "unexpected status: check that host and port point at a SymetryML REST server",
+                ),
+            }
+            Some(response)
+        }
+        Err(e) => {
+            report.fail(
+                "authentication",
+                e.to_string(),
+                "the REST request failed: check the tls setting and the server logs",
+            );
+            None
+        }
+    }
+}
+
+/// Compare the local clock with the server's `Date` header
+fn check_clock(report: &mut Report, date: &str) {
+    let server = match DateTime::parse_from_rfc2822(date) {
+        Ok(d) => d.with_timezone(&Utc),
+        Err(e) => {
+            report.skip("clock", format!("cannot parse Date header '{}': {}", date, e));
+            return;
+        }
+    };
+
+    let skew = Utc::now().signed_duration_since(server).num_seconds();
+    let detail = match skew {
+        0 => "local clock matches the server".to_string(),
+        s if s > 0 => format!("local clock is {}s ahead of the server", s),
+        s => format!("local clock is {}s behind the server", -s),
+    };
+
+    if skew.unsigned_abs() > MAX_CLOCK_SKEW.as_secs() {
+        report.fail(
+            "clock",
+            detail,
+            "synchronize the local clock (e.g. with NTP): requests are signed with the local time",
+        );
+    } else {
+        report.pass("clock", detail);
+    }
+}
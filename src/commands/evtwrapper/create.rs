@@ -0,0 +1,37 @@
+use crate::commands;
+use crate::common::parse_key_value_list;
+use crate::output;
+use serde_json::json;
+use sym_rest_rc::{SymClient, SymError};
+
+pub async fn handle_create(
+    project: String,
+    model: String,
+    params: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    msg!("Creating EVT wrapper for model '{}' in project '{}'", model, project);
+    msg!("Parameters: {}", params);
+
+    let body_params = parse_key_value_list(&params)?;
+
+    let client = SymClient::from_config()?;
+    let Some(response) = commands::send(client.create_evtwrapper(&project, &model, &body_params)).await? else {
+        return Ok(());
+    };
+
+    if response.is_success() {
+        msg!("EVT wrapper created successfully!");
+        msg!("Response: {}", response.body);
+    } else {
+        msg!("Failed to create EVT wrapper. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
+    }
+
+    output::emit(&json!({
+        "project": project,
+        "model": model,
+        "status": response.status.as_u16(),
+        "response": response.body_value(),
+    }))
+}
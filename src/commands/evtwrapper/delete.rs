@@ -0,0 +1,35 @@
+use crate::commands;
+use crate::output;
+use serde_json::json;
+use sym_rest_rc::{SymClient, SymError};
+
+pub async fn handle_delete(
+    project: String,
+    model: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    msg!("Deleting EVT wrapper from model '{}' in project '{}'", model, project);
+
+    let client = SymClient::from_config()?;
+    let Some(response) = commands::send(client.delete_evtwrapper(&project, &model)).await? else {
+        return Ok(());
+    };
+
+    if response.is_success() {
+        msg!("EVT wrapper deleted successfully from model '{}'!", model);
+        if !response.body.is_empty() {
+            msg!("Response: {}", response.body);
+        }
+    } else {
+        msg!("Failed to delete EVT wrapper. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
+    }
+
+    output::emit(&json!({
+        "project": project,
+        "model": model,
+        "deleted": true,
+        "status": response.status.as_u16(),
+        "response": response.body_value(),
+    }))
+}
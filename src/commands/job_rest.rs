@@ -1,60 +1,65 @@
-use crate::config;
-use crate::auth::AuthHeaders;
+use crate::commands;
+use crate::output;
+use std::time::{Duration, Instant};
+use sym_rest_rc::{config, JobState, JobStatus, PollOptions, SymClient, SymError};
 
 pub async fn handle_job(
     job_id: String,
+    watch: Option<Duration>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Checking status for job: {}", job_id);
-
-    // Build the resource path
-    let resource = format!(
-        "/symetry/rest/{}/jobs/{}",
-        config::Config::user(),
-        job_id
-    );
-
-    // Build the full URL
-    let url = format!(
-        "http://{}:{}{}",
-        config::Config::host(),
-        config::Config::port(),
-        resource
-    );
-
-    // Generate authentication headers
-    let auth = AuthHeaders::generate(
-        "GET",
-        &resource,
-        None,
-        None,
-        config::Config::user(),
-        &config::Config::secretkey(),
-    )?;
-
-    // Create HTTP client and make the request
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Content-MD5", &auth.content_md5)
-        .header("Sym-date", &auth.sym_date)
-        .header("Customer-ID", config::Config::user())
-        .header("Authorization", &auth.authorization)
-        .header("sym-version", "6.3")
-        .send()
-        .await?;
+    let client = SymClient::from_config()?;
+
+    // A dry run prints the status request instead of polling
+    if let Some(timeout) = watch.filter(|_| config::Config::dry_run().is_none()) {
+        let status = wait_for_job(&client, &job_id, timeout).await?;
+        return output::emit(&status);
+    }
 
-    // Check response status
-    let status = response.status();
-    let response_text = response.text().await?;
+    msg!("Checking status for job: {}", job_id);
 
-    if status.is_success() {
-        println!("Job status retrieved successfully!");
-        println!("Response: {}", response_text);
+    let Some(response) = commands::send(client.job_status(&job_id)).await? else {
+        return Ok(());
+    };
+
+    if response.is_success() {
+        msg!("Job status retrieved successfully!");
+        let status = JobStatus::from_response(&job_id, &response)?;
+        output::emit_with_text(&status, &status)?;
     } else {
-        println!("Failed to get job status. Status: {}", status);
-        println!("Response: {}", response_text);
-        return Err(format!("Request failed with status: {}", status).into());
+        msg!("Failed to get job status. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
     }
 
     Ok(())
 }
+
+/// Poll a job until it is done, printing every state transition
+/// Returns the final status, or an error if the job failed or the timeout elapsed
+pub async fn wait_for_job(
+    client: &SymClient,
+    job_id: &str,
+    timeout: Duration,
+) -> Result<JobStatus, Box<dyn std::error::Error>> {
+    msg!("Waiting for job {} (timeout: {}s)", job_id, timeout.as_secs());
+
+    let started = Instant::now();
+    let status = client
+        .wait_for_job(job_id, &PollOptions::with_timeout(timeout), |s| {
+            msg!("[{:>6.1}s] Job {}: {}", started.elapsed().as_secs_f64(), s.job_id, s.state);
+        })
+        .await?;
+
+    msg!("{}", status.to_string().trim_end());
+
+    if status.state == JobState::Done {
+        msg!("Job {} finished successfully!", job_id);
+        Ok(status)
+    } else {
+        Err(SymError::Job {
+            job_id: job_id.to_string(),
+            message: format!("Job {} failed", job_id),
+        }
+        .into())
+    }
+}
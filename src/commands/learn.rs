@@ -0,0 +1,173 @@
+use crate::commands;
+use crate::commands::job_rest::wait_for_job;
+use crate::common::DataFrame;
+use crate::output;
+use serde_json::{json, Value};
+use std::time::Duration;
+use sym_rest_rc::data::{CsvBatchReader, CsvOptions};
+use sym_rest_rc::infer::{infer_file_types, is_auto, parse_types, DEFAULT_SAMPLE_ROWS};
+use sym_rest_rc::transport::{self, Reply, TransportKind};
+use sym_rest_rc::{config, SymClient};
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_learn(
+    project: String,
+    file: String,
+    csv: CsvOptions,
+    types: Option<String>,
+    kind: TransportKind,
+    batch_rows: Option<usize>,
+    window: usize,
+    wait: Option<Duration>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    msg!("Learning from file: {} for project: {} (transport: {})", file, project, kind);
+
+    // Read the whole file as one batch unless --batch-rows is given.
+    // REST requests carry whole decimal values as integers
+    let mut reader = CsvBatchReader::open(&file, batch_rows.unwrap_or(usize::MAX), &csv)?
+        .normalize_numbers(kind == TransportKind::Rest);
+    let attribute_names = reader.attribute_names().to_vec();
+
+    // Resolve attribute types, inferring them from a sample of the file if needed
+    let attribute_types = match types.as_deref() {
+        Some(t) if !is_auto(Some(t)) => parse_types(t, attribute_names.len())?,
+        _ => {
+            let inferred = infer_file_types(&file, &csv, DEFAULT_SAMPLE_ROWS)?;
+            msg!("Inferred attribute types: {}", inferred.join(","));
+            inferred
+        }
+    };
+
+    let to_dataframe = |data| DataFrame {
+        attribute_names: attribute_names.clone(),
+        data,
+        attribute_types: attribute_types.clone(),
+        error_handling: Some(1),
+    };
+
+    // A dry run prints the first batch as it would be sent, and stops there
+    if config::Config::dry_run().is_some() {
+        let dataframe = to_dataframe(reader.next_batch()?.unwrap_or_default());
+        return commands::print_dry_run(&transport::prepare_learn(kind, &project, &dataframe)?);
+    }
+
+    // One transport for the whole file; over WebSocket up to `window` batches are in flight
+    let mut sender = transport::open_learn(kind, &project, window).await?;
+
+    // Jobs are polled over REST, whatever the transport
+    let poll = match wait {
+        Some(timeout) => Some((SymClient::from_config()?, timeout)),
+        None => None,
+    };
+
+    let mut batch_number = 0;
+    let mut total_rows = 0;
+    let mut progress = LearnProgress::default();
+
+    while let Some(data) = reader.next_batch()? {
+        batch_number += 1;
+        let rows = data.len();
+
+        if batch_rows.is_some() {
+            let mut line = format!("Batch {}: rows {}-{}", batch_number, total_rows + 1, total_rows + rows);
+            if let Some(p) = reader.progress() {
+                line.push_str(&format!(" ({:.1}% of file)", p * 100.0));
+            }
+            msg!("{}", line);
+        } else {
+            msg!("Loaded {} rows with {} columns", rows, attribute_names.len());
+        }
+        total_rows += rows;
+
+        for reply in sender.send(&to_dataframe(data)).await? {
+            progress.record(reply)?;
+        }
+    }
+
+    // Wait for the remaining replies
+    for reply in sender.finish().await? {
+        progress.record(reply)?;
+    }
+
+    // The jobs are waited on once every batch is sent, so a slow job does not hold up the upload
+    if let Some((client, timeout)) = &poll {
+        progress.wait_for_jobs(client, *timeout).await?;
+    }
+
+    if batch_number == 0 {
+        msg!("No data rows found in {}", file);
+    } else if batch_rows.is_some() {
+        msg!("Learned {} rows in {} batches", total_rows, batch_number);
+    }
+
+    output::emit(&json!({
+        "project": project,
+        "transport": kind.to_string(),
+        "rows": total_rows,
+        "batches": progress.batches,
+        "lastBatch": progress.last_batch,
+    }))
+}
+
+/// Totals of the batches accepted so far
+/// Only the reply to the last batch is kept, so memory use does not grow with the file
+#[derive(Default)]
+struct LearnProgress {
+    accepted_rows: usize,
+    batches: usize,
+    last_batch: Option<Value>,
+    /// IDs of the jobs started by the accepted batches, in order
+    job_ids: Vec<String>,
+}
+
+impl LearnProgress {
+    /// Record the reply to a batch, failing if the server rejected it
+    fn record(&mut self, reply: Reply) -> Result<(), Box<dyn std::error::Error>> {
+        if reply.is_error() {
+            if let Some(status) = reply.status {
+                msg!("Failed to learn batch {}. Status: {}", reply.seq, status);
+            }
+            msg!("Response: {}", reply.body);
+            msg!("Batch {} failed ({} rows learned before the failure)", reply.seq, self.accepted_rows);
+            return Err(reply.error().into());
+        }
+
+        match reply.status {
+            Some(status) => msg!("Batch {} accepted. Status: {}", reply.seq, status),
+            None => msg!("Batch {} acknowledged", reply.seq),
+        }
+        if let Some(jid) = &reply.job_id {
+            msg!("Job ID: {}", jid);
+        }
+        msg!("Response: {}", reply.body);
+
+        self.job_ids.extend(reply.job_id.clone());
+        self.accepted_rows += reply.rows;
+        self.batches += 1;
+        self.last_batch = Some(json!({
+            "batch": reply.seq,
+            "rows": reply.rows,
+            "status": reply.status.map(|s| s.as_u16()),
+            "jobId": reply.job_id,
+            "response": reply.body_value(),
+        }));
+        Ok(())
+    }
+
+    /// Wait for every job in turn, each with the full timeout,
+    /// and add the final status of the last one to the last batch
+    async fn wait_for_jobs(&mut self, client: &SymClient, timeout: Duration) -> Result<(), Box<dyn std::error::Error>> {
+        if self.job_ids.is_empty() {
+            msg!("No job ID returned, nothing to wait for");
+            return Ok(());
+        }
+        let mut job = None;
+        for jid in &self.job_ids {
+            job = Some(wait_for_job(client, jid, timeout).await?);
+        }
+        if let Some(last) = self.last_batch.as_mut() {
+            last["job"] = json!(job);
+        }
+        Ok(())
+    }
+}
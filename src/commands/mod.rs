@@ -1,6 +1,31 @@
 pub mod projects;
 pub mod models;
 pub mod evtwrapper;
-pub mod learn_rest;
-pub mod learn_ws;
+pub mod learn;
 pub mod job_rest;
+pub mod doctor;
+pub mod run;
+pub mod raw;
+
+use crate::output;
+use sym_rest_rc::client::Call;
+use sym_rest_rc::transport::Prepared;
+use sym_rest_rc::{config, SymResponse};
+
+/// Send a request, or in a dry run print it instead of sending it
+/// Returns None in a dry run: the command has nothing more to do
+pub async fn send(call: Call<'_>) -> Result<Option<SymResponse>, Box<dyn std::error::Error>> {
+    if config::Config::dry_run().is_some() {
+        print_dry_run(&Prepared::Rest(call.prepare()?))?;
+        return Ok(None);
+    }
+    Ok(Some(call.await?))
+}
+
+/// Print a request signed by a dry run as the result of the command
+pub fn print_dry_run(request: &Prepared) -> Result<(), Box<dyn std::error::Error>> {
+    let show_secret = config::Config::dry_run().map(|d| d.show_secret).unwrap_or(false);
+    let mut result = request.to_value(show_secret);
+    result["dryRun"] = true.into();
+    output::emit_with_text(&result, &format!("{}\n", request.display(show_secret)))
+}
@@ -1,88 +1,64 @@
-use crate::config;
-use crate::auth::AuthHeaders;
-use crate::common::{DataFrame, MLContext, parse_int_list_as_strings, parse_string_list};
+use crate::commands;
+use crate::commands::job_rest::wait_for_job;
+use crate::output;
+use serde_json::json;
+use crate::common::{DataFrame, MLContext, parse_int_list_as_strings, parse_key_value_list, parse_string_list};
 use std::collections::HashMap;
-use serde::Serialize;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-#[derive(Serialize, Debug)]
-struct AutoSelectRequest {
-    dataframe: DataFrame,
-    mlcontext: MLContext,
-}
+use sym_rest_rc::data::{CsvBatchReader, CsvOptions};
+use sym_rest_rc::infer::{infer_types, is_auto, parse_types};
+use std::time::Duration;
+use sym_rest_rc::{SymClient, SymError};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_autoselect(
     project: String,
     model_name: String,
     task: String,
     val_file: Option<String>,
     val_df: Option<String>,
+    csv: CsvOptions,
+    types: Option<String>,
     targets: Option<String>,
     inputs: Option<String>,
     target_names: Option<String>,
     input_names: Option<String>,
     params: Option<String>,
+    wait: Option<Duration>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Auto-selecting algorithm for model: {} in project: {} (task: {})", model_name, project, task);
+    msg!("Auto-selecting algorithm for model: {} in project: {} (task: {})", model_name, project, task);
 
     // Check that either val_df or val_file is provided
     if val_df.is_none() && val_file.is_none() {
-        return Err("Must specify either --val-df (JSON dataframe) or --val-file (data file)".into());
+        return Err(SymError::Usage("Must specify either --val-df (JSON dataframe) or --val-file (data file)".to_string()).into());
     }
 
     if val_df.is_some() && val_file.is_some() {
-        return Err("Cannot specify both --val-df and --val-file. Choose one.".into());
+        return Err(SymError::Usage("Cannot specify both --val-df and --val-file. Choose one.".to_string()).into());
     }
 
     // Parse the validation data
     let dataframe = if let Some(json_df) = val_df {
-        println!("Using JSON validation dataframe");
+        msg!("Using JSON validation dataframe");
         serde_json::from_str::<DataFrame>(&json_df)?
     } else if let Some(file_path) = val_file {
-        println!("Reading validation data from file: {}", file_path);
+        msg!("Reading validation data from file: {}", file_path);
 
         // Read CSV file
-        let file_handle = File::open(&file_path)?;
-        let reader = BufReader::new(file_handle);
-        let mut lines = reader.lines();
-
-        // Read header (first line)
-        let header_line = lines.next()
-            .ok_or("Empty CSV file")??;
-        let attribute_names: Vec<String> = header_line
-            .split(',')
-            .map(|s| s.trim().to_string())
-            .collect();
-
-        // Read data rows
-        let mut data: Vec<Vec<String>> = Vec::new();
-        for line in lines {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue; // Skip empty lines
+        let reader = CsvBatchReader::open(&file_path, usize::MAX, &csv)?.normalize_numbers(true);
+        let attribute_names = reader.attribute_names().to_vec();
+        let data = reader.read_all()?;
+
+        msg!("Loaded {} rows with {} columns", data.len(), attribute_names.len());
+
+        // Resolve attribute types, inferring them from the data if needed
+        let attribute_types = match types.as_deref() {
+            Some(t) if !is_auto(Some(t)) => parse_types(t, attribute_names.len())?,
+            _ => {
+                let inferred = infer_types(attribute_names.len(), &data);
+                msg!("Inferred attribute types: {}", inferred.join(","));
+                inferred
             }
-            let row: Vec<String> = line
-                .split(',')
-                .map(|s| {
-                    let trimmed = s.trim();
-                    // Try to clean up decimal values that should be integers
-                    if let Ok(val) = trimmed.parse::<f64>() {
-                        if val.fract() == 0.0 {
-                            // It's a whole number, return as integer string
-                            return format!("{}", val as i64);
-                        }
-                    }
-                    trimmed.to_string()
-                })
-                .collect();
-            data.push(row);
-        }
-
-        println!("Loaded {} rows with {} columns", data.len(), attribute_names.len());
-
-        // For validation data, we need to infer types or use "C" for continuous
-        let attribute_types: Vec<String> = vec!["C".to_string(); attribute_names.len()];
+        };
 
         DataFrame {
             attribute_names,
@@ -96,7 +72,7 @@ pub async fn handle_autoselect(
 
     // Parse targets (IDs) - convert to strings for server
     let targets_vec = if let Some(t) = targets {
-        println!("Targets (IDs): {}", t);
+        msg!("Targets (IDs): {}", t);
         Some(parse_int_list_as_strings(&t)?)
     } else {
         None
@@ -104,7 +80,7 @@ pub async fn handle_autoselect(
 
     // Parse inputs (IDs) - convert to strings for server
     let inputs_vec = if let Some(i) = inputs {
-        println!("Inputs (IDs): {}", i);
+        msg!("Inputs (IDs): {}", i);
         Some(parse_int_list_as_strings(&i)?)
     } else {
         None
@@ -112,7 +88,7 @@ pub async fn handle_autoselect(
 
     // Parse target names
     let target_names_vec = if let Some(tn) = target_names {
-        println!("Target Names: {}", tn);
+        msg!("Target Names: {}", tn);
         Some(parse_string_list(&tn))
     } else {
         None
@@ -120,23 +96,19 @@ pub async fn handle_autoselect(
 
     // Parse input names
     let input_names_vec = if let Some(in_) = input_names {
-        println!("Input Names: {}", in_);
+        msg!("Input Names: {}", in_);
         Some(parse_string_list(&in_))
     } else {
         None
     };
 
     // Parse extra parameters
-    let mut extra_params: HashMap<String, String> = HashMap::new();
-    if let Some(p) = params {
-        println!("Parameters: {}", p);
-        for pair in p.split(',') {
-            let parts: Vec<&str> = pair.trim().split('=').collect();
-            if parts.len() == 2 {
-                extra_params.insert(parts[0].to_string(), parts[1].to_string());
-            }
-        }
-    }
+    let extra_params: HashMap<String, String> = if let Some(p) = params {
+        msg!("Parameters: {}", p);
+        parse_key_value_list(&p)?
+    } else {
+        HashMap::new()
+    };
 
     // Build the MLContext
     let ml_context = MLContext {
@@ -147,84 +119,43 @@ pub async fn handle_autoselect(
         extra_parameters: extra_params,
     };
 
-    // Build the request body
-    let request_body = AutoSelectRequest {
-        dataframe,
-        mlcontext: ml_context,
+    let client = SymClient::from_config()?;
+    let Some(response) =
+        commands::send(client.autoselect(&project, &model_name, &task, &dataframe, &ml_context)).await?
+    else {
+        return Ok(());
     };
 
-    // Build the resource path
-    let resource = format!(
-        "/symetry/rest/{}/projects/{}/autoSelect",
-        config::Config::user(),
-        project
-    );
-
-    // Build query string with task and modelid
-    let query = format!("task={}&modelid={}", task, model_name);
-
-    // Build the full URL
-    let url = format!(
-        "http://{}:{}{}?{}",
-        config::Config::host(),
-        config::Config::port(),
-        resource,
-        query
-    );
-
-    // Convert body to JSON string
-    let body_json = serde_json::to_string(&request_body)?;
-    println!("Request body length: {} bytes", body_json.len());
-    println!("DEBUG - Full request body: {}", body_json);
-
-    // Generate authentication headers
-    let auth = AuthHeaders::generate(
-        "POST",
-        &resource,
-        Some(&query),
-        Some(&body_json),
-        config::Config::user(),
-        &config::Config::secretkey(),
-    )?;
-
-    // Create HTTP client and make the request
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("Content-MD5", &auth.content_md5)
-        .header("Sym-date", &auth.sym_date)
-        .header("Customer-ID", config::Config::user())
-        .header("Authorization", &auth.authorization)
-        .header("sym-version", "6.3")
-        .header("Content-Type", "application/json")
-        .body(body_json)
-        .send()
-        .await?;
-
-    // Check response status
-    let status = response.status();
-
-    // Extract the sym-job-id header if present
-    let job_id = response.headers()
-        .get("sym-job-id")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
-    let response_text = response.text().await?;
-
-    if status.is_success() {
-        println!("Auto-select request submitted successfully!");
-        if let Some(job_id) = job_id {
-            println!("Job ID: {}", job_id);
-            println!("You can check the job status with:");
-            println!("  job --id={}", job_id);
+    if response.is_success() {
+        msg!("Auto-select request submitted successfully!");
+        if let Some(job_id) = &response.job_id {
+            msg!("Job ID: {}", job_id);
+            if wait.is_none() {
+                msg!("You can check the job status with:");
+                msg!("  job --id={}", job_id);
+            }
         }
-        println!("Response: {}", response_text);
+        msg!("Response: {}", response.body);
     } else {
-        println!("Failed to auto-select model. Status: {}", status);
-        println!("Response: {}", response_text);
-        return Err(format!("Request failed with status: {}", status).into());
+        msg!("Failed to auto-select model. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
+    }
+
+    let mut job = None;
+    if let Some(timeout) = wait {
+        match &response.job_id {
+            Some(job_id) => job = Some(wait_for_job(&client, job_id, timeout).await?),
+            None => msg!("No job ID returned, nothing to wait for"),
+        }
     }
 
-    Ok(())
+    output::emit(&json!({
+        "project": project,
+        "model": model_name,
+        "status": response.status.as_u16(),
+        "jobId": response.job_id,
+        "response": response.body_value(),
+        "job": job,
+    }))
 }
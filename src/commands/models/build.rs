@@ -1,8 +1,13 @@
-use crate::config;
-use crate::auth::AuthHeaders;
-use crate::common::{MLContext, parse_int_list_as_strings, parse_string_list};
+use crate::commands;
+use crate::commands::job_rest::wait_for_job;
+use crate::output;
+use serde_json::json;
+use crate::common::{MLContext, parse_int_list_as_strings, parse_key_value_list, parse_string_list};
 use std::collections::HashMap;
+use std::time::Duration;
+use sym_rest_rc::{SymClient, SymError};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_build(
     project: String,
     model_name: String,
@@ -12,24 +17,25 @@ pub async fn handle_build(
     target_names: Option<String>,
     input_names: Option<String>,
     params: Option<String>,
+    wait: Option<Duration>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Building model: {} (type: {}) for project: {}", model_name, model_type, project);
+    msg!("Building model: {} (type: {}) for project: {}", model_name, model_type, project);
 
     // Check which form is being used
     let using_ids = targets.is_some() || inputs.is_some();
     let using_names = target_names.is_some() || input_names.is_some();
 
     if using_ids && using_names {
-        return Err("Cannot mix ID-based and name-based parameters".into());
+        return Err(SymError::Usage("Cannot mix ID-based and name-based parameters".to_string()).into());
     }
 
     if !using_ids && !using_names {
-        return Err("Must specify either targets/inputs (IDs) or targetNames/inputNames (names)".into());
+        return Err(SymError::Usage("Must specify either targets/inputs (IDs) or targetNames/inputNames (names)".to_string()).into());
     }
 
     // Parse targets (IDs) - convert to strings for server
     let targets_vec = if let Some(t) = targets {
-        println!("Targets (IDs): {}", t);
+        msg!("Targets (IDs): {}", t);
         Some(parse_int_list_as_strings(&t)?)
     } else {
         None
@@ -37,7 +43,7 @@ pub async fn handle_build(
 
     // Parse inputs (IDs) - convert to strings for server
     let inputs_vec = if let Some(i) = inputs {
-        println!("Inputs (IDs): {}", i);
+        msg!("Inputs (IDs): {}", i);
         Some(parse_int_list_as_strings(&i)?)
     } else {
         None
@@ -45,7 +51,7 @@ pub async fn handle_build(
 
     // Parse target names
     let target_names_vec = if let Some(tn) = target_names {
-        println!("Target Names: {}", tn);
+        msg!("Target Names: {}", tn);
         Some(parse_string_list(&tn))
     } else {
         None
@@ -53,23 +59,19 @@ pub async fn handle_build(
 
     // Parse input names
     let input_names_vec = if let Some(in_) = input_names {
-        println!("Input Names: {}", in_);
+        msg!("Input Names: {}", in_);
         Some(parse_string_list(&in_))
     } else {
         None
     };
 
     // Parse extra parameters
-    let mut extra_params: HashMap<String, String> = HashMap::new();
-    if let Some(p) = params {
-        println!("Parameters: {}", p);
-        for pair in p.split(',') {
-            let parts: Vec<&str> = pair.trim().split('=').collect();
-            if parts.len() == 2 {
-                extra_params.insert(parts[0].to_string(), parts[1].to_string());
-            }
-        }
-    }
+    let extra_params: HashMap<String, String> = if let Some(p) = params {
+        msg!("Parameters: {}", p);
+        parse_key_value_list(&p)?
+    } else {
+        HashMap::new()
+    };
 
     // Build the MLContext
     let ml_context = MLContext {
@@ -80,77 +82,44 @@ pub async fn handle_build(
         extra_parameters: extra_params,
     };
 
-    // Build the resource path
-    let resource = format!(
-        "/symetry/rest/{}/projects/{}/build",
-        config::Config::user(),
-        project
-    );
-
-    // Build query string
-    let query = format!("algo={}&modelid={}", model_type, model_name);
-
-    // Build the full URL
-    let url = format!(
-        "http://{}:{}{}?{}",
-        config::Config::host(),
-        config::Config::port(),
-        resource,
-        query
-    );
-
-    // Convert body to JSON string
-    let body_json = serde_json::to_string(&ml_context)?;
-    println!("Request body: {}", body_json);
-
-    // Generate authentication headers
-    let auth = AuthHeaders::generate(
-        "POST",
-        &resource,
-        Some(&query),
-        Some(&body_json),
-        config::Config::user(),
-        &config::Config::secretkey(),
-    )?;
+    // Print the request body
+    msg!("Request body: {}", serde_json::to_string(&ml_context)?);
 
-    // Create HTTP client and make the request
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .header("Content-MD5", &auth.content_md5)
-        .header("Sym-date", &auth.sym_date)
-        .header("Customer-ID", config::Config::user())
-        .header("Authorization", &auth.authorization)
-        .header("sym-version", "6.3")
-        .header("Content-Type", "application/json")
-        .body(body_json)
-        .send()
-        .await?;
-
-    // Check response status
-    let status = response.status();
-
-    // Extract the sym-job-id header if present
-    let job_id = response.headers()
-        .get("sym-job-id")
-        .and_then(|h| h.to_str().ok())
-        .map(|s| s.to_string());
-
-    let response_text = response.text().await?;
+    let client = SymClient::from_config()?;
+    let Some(response) = commands::send(client.build(&project, &model_name, &model_type, &ml_context)).await? else {
+        return Ok(());
+    };
 
-    if status.is_success() {
-        println!("Model build request submitted successfully!");
-        if let Some(job_id) = job_id {
-            println!("Job ID: {}", job_id);
-            println!("You can check the job status with:");
-            println!("  job --id={}", job_id);
+    if response.is_success() {
+        msg!("Model build request submitted successfully!");
+        if let Some(job_id) = &response.job_id {
+            msg!("Job ID: {}", job_id);
+            if wait.is_none() {
+                msg!("You can check the job status with:");
+                msg!("  job --id={}", job_id);
+            }
         }
-        println!("Response: {}", response_text);
+        msg!("Response: {}", response.body);
     } else {
-        println!("Failed to build model. Status: {}", status);
-        println!("Response: {}", response_text);
-        return Err(format!("Request failed with status: {}", status).into());
+        msg!("Failed to build model. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
+    }
+
+    let mut job = None;
+    if let Some(timeout) = wait {
+        match &response.job_id {
+            Some(job_id) => job = Some(wait_for_job(&client, job_id, timeout).await?),
+            None => msg!("No job ID returned, nothing to wait for"),
+        }
     }
 
-    Ok(())
+    output::emit(&json!({
+        "project": project,
+        "model": model_name,
+        "status": response.status.as_u16(),
+        "jobId": response.job_id,
+        "response": response.body_value(),
+        "job": job,
+    }))
 }
@@ -1,61 +1,28 @@
-use crate::config;
-use crate::auth::AuthHeaders;
+use crate::commands;
+use crate::output;
+use sym_rest_rc::{ModelInfo, SymClient, SymError};
 
 pub async fn handle_info(
     project: String,
     model: String,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Getting information for model '{}' in project '{}'", model, project);
-
-    // Build the resource path
-    let resource = format!(
-        "/symetry/rest/{}/projects/{}/models/{}",
-        config::Config::user(),
-        project,
-        model
-    );
-
-    // Build the full URL
-    let url = format!(
-        "http://{}:{}{}",
-        config::Config::host(),
-        config::Config::port(),
-        resource
-    );
-
-    // Generate authentication headers (no body for GET)
-    let auth = AuthHeaders::generate(
-        "GET",
-        &resource,
-        None,
-        None,
-        config::Config::user(),
-        &config::Config::secretkey(),
-    )?;
-
-    // Create HTTP client and make the request
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .header("Content-MD5", &auth.content_md5)
-        .header("Sym-date", &auth.sym_date)
-        .header("Customer-ID", config::Config::user())
-        .header("Authorization", &auth.authorization)
-        .header("sym-version", "6.3")
-        .send()
-        .await?;
-
-    // Check response status
-    let status = response.status();
-    let response_text = response.text().await?;
-
-    if status.is_success() {
-        println!("Model information retrieved successfully!");
-        println!("{}", response_text);
+    msg!("Getting information for model '{}' in project '{}'", model, project);
+
+    let client = SymClient::from_config()?;
+    let Some(response) = commands::send(client.model_info(&project, &model)).await? else {
+        return Ok(());
+    };
+
+    if response.is_success() {
+        msg!("Model information retrieved successfully!");
+        match ModelInfo::from_body(&response.body) {
+            Ok(info) => output::emit_with_text(&info, &info)?,
+            Err(_) => output::emit_with_text(&response.body_value(), &format!("{}\n", response.body))?,
+        }
     } else {
-        println!("Failed to get model information. Status: {}", status);
-        println!("Response: {}", response_text);
-        return Err(format!("Request failed with status: {}", status).into());
+        msg!("Failed to get model information. Status: {}", response.status);
+        msg!("Response: {}", response.body);
+        return Err(SymError::from_response(&response).into());
     }
 
     Ok(())
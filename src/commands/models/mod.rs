@@ -2,5 +2,4 @@ pub mod build;
 pub mod delete;
 pub mod autoselect;
 pub mod info;
-pub mod predict_rest;
-pub mod predict_ws;
+pub mod predict;
@@ -0,0 +1,204 @@
+use crate::commands;
+use crate::common::DataFrame;
+use crate::output::{self, OutputFormat};
+use serde_json::{json, Map, Value};
+use std::collections::VecDeque;
+use sym_rest_rc::data::{CsvBatchReader, CsvOptions, PredictionWriter};
+use sym_rest_rc::infer::{infer_file_types, is_auto, parse_types, DEFAULT_SAMPLE_ROWS};
+use sym_rest_rc::transport::{self, Reply, TransportKind};
+use sym_rest_rc::{config, Predictions, SymError};
+
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_predict(
+    project: String,
+    model: String,
+    df: Option<String>,
+    file: Option<String>,
+    csv: CsvOptions,
+    types: Option<String>,
+    out: Option<String>,
+    kind: TransportKind,
+    batch_rows: Option<usize>,
+    window: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    msg!("Making prediction with project: {} and model: {} (transport: {})", project, model, kind);
+
+    // Check that either df or file is provided
+    if df.is_none() && file.is_none() {
+        return Err(SymError::Usage("Must specify either --df (JSON dataframe) or --file (data file)".to_string()).into());
+    }
+
+    if df.is_some() && file.is_some() {
+        return Err(SymError::Usage("Cannot specify both --df and --file. Choose one.".to_string()).into());
+    }
+
+    // Input batches: the JSON dataframe as a single batch, or the file read
+    // incrementally in batches of --batch-rows rows (the whole file by default)
+    let mut input = if let Some(json_df) = df {
+        msg!("Using JSON dataframe: {}", json_df);
+        PredictInput::Single(Some(serde_json::from_str(&json_df)?))
+    } else if let Some(file_path) = file {
+        msg!("Reading data from file: {}", file_path);
+
+        // REST requests carry whole decimal values as integers
+        let reader = CsvBatchReader::open(&file_path, batch_rows.unwrap_or(usize::MAX), &csv)?
+            .normalize_numbers(kind == TransportKind::Rest);
+        let num_columns = reader.attribute_names().len();
+
+        // Resolve attribute types, inferring them from a sample of the file if needed
+        let attribute_types = match types.as_deref() {
+            Some(t) if !is_auto(Some(t)) => parse_types(t, num_columns)?,
+            _ => {
+                let inferred = infer_file_types(&file_path, &csv, DEFAULT_SAMPLE_ROWS)?;
+                msg!("Inferred attribute types: {}", inferred.join(","));
+                inferred
+            }
+        };
+
+        PredictInput::File(Box::new(reader), attribute_types)
+    } else {
+        unreachable!()
+    };
+
+    // A dry run prints the first batch as it would be sent, and stops there
+    if config::Config::dry_run().is_some() {
+        return match input.next_batch()? {
+            Some(dataframe) => commands::print_dry_run(&transport::prepare_predict(kind, &project, &model, &dataframe)?),
+            None => {
+                msg!("No data rows, nothing to send");
+                Ok(())
+            }
+        };
+    }
+
+    // Create the output file first so a bad path fails before the request is sent
+    let mut writer = out.as_deref().map(PredictionWriter::create).transpose()?;
+
+    let mut sender = transport::open_predict(kind, &project, &model, window).await?;
+
+    // Batches sent but not answered yet, oldest first. The window bounds how many
+    // are held, and replies arrive in the order the batches were sent.
+    let mut pending: VecDeque<DataFrame> = VecDeque::new();
+    let mut results = PredictResults::default();
+    let mut batch_number = 0;
+    let mut sent_rows = 0;
+
+    while let Some(dataframe) = input.next_batch()? {
+        batch_number += 1;
+        let rows = dataframe.data.len();
+        if batch_rows.is_some() {
+            msg!("Batch {}: rows {}-{}", batch_number, sent_rows + 1, sent_rows + rows);
+        } else {
+            msg!("Loaded {} rows with {} columns", rows, dataframe.attribute_names.len());
+        }
+        sent_rows += rows;
+
+        let replies = sender.send(&dataframe).await?;
+        pending.push_back(dataframe);
+        for reply in replies {
+            results.record(reply, pending.pop_front().unwrap(), writer.as_mut())?;
+        }
+    }
+
+    // Wait for the remaining replies
+    for reply in sender.finish().await? {
+        results.record(reply, pending.pop_front().unwrap(), writer.as_mut())?;
+    }
+
+    if let (Some(writer), Some(path)) = (writer, &out) {
+        let rows = writer.finish()?;
+        msg!("Wrote {} rows to {}", rows, path);
+    }
+    if batch_rows.is_some() {
+        msg!("Scored {} rows in {} batches", results.rows, results.batches);
+    }
+
+    let result = json!({
+        "project": project,
+        "model": model,
+        "transport": kind.to_string(),
+        "rows": results.rows,
+        "batches": results.batches,
+        "out": out,
+    });
+    // Without --out the predictions were already written to stdout, one line per batch
+    match out {
+        Some(_) => output::emit(&result),
+        None => output::emit_streamed(&result),
+    }
+}
+
+/// Source of the DataFrames to score
+enum PredictInput {
+    Single(Option<DataFrame>),
+    File(Box<CsvBatchReader<std::fs::File>>, Vec<String>),
+}
+
+impl PredictInput {
+    /// Get the next DataFrame to send, or None when the input is exhausted
+    fn next_batch(&mut self) -> Result<Option<DataFrame>, Box<dyn std::error::Error>> {
+        match self {
+            PredictInput::Single(df) => Ok(df.take()),
+            PredictInput::File(reader, attribute_types) => Ok(reader.next_batch()?.map(|data| DataFrame {
+                attribute_names: reader.attribute_names().to_vec(),
+                data,
+                attribute_types: attribute_types.clone(),
+                error_handling: None,
+            })),
+        }
+    }
+}
+
+/// Totals of the batches scored so far
+/// Predictions are written as they arrive and not kept, so memory use does not grow with the input
+#[derive(Default)]
+struct PredictResults {
+    batches: usize,
+    rows: usize,
+}
+
+impl PredictResults {
+    /// Record the reply to a batch and write its predictions to the output file,
+    /// or to stdout in the selected format without one
+    fn record(
+        &mut self,
+        reply: Reply,
+        dataframe: DataFrame,
+        writer: Option<&mut PredictionWriter>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if reply.is_error() {
+            msg!("Response: {}", reply.body);
+            if let Some(status) = reply.status {
+                msg!("Failed to make prediction. Status: {}", status);
+            }
+            msg!("Batch {} failed ({} rows scored before the failure)", reply.seq, self.rows);
+            return Err(reply.error().into());
+        }
+
+        let predictions = reply.predictions()?;
+        match writer {
+            Some(writer) => writer.write_batch(&dataframe, &predictions)?,
+            None => output::emit_line(&json!({
+                "batch": reply.seq,
+                "rows": reply.rows,
+                "predictions": match output::format() {
+                    OutputFormat::Table => json!(prediction_rows(&predictions)),
+                    _ => json!(predictions),
+                },
+            }))?,
+        }
+
+        self.batches += 1;
+        self.rows += reply.rows;
+        Ok(())
+    }
+}
+
+/// Predictions as one object per row, keyed by attribute name, so a table gets a column per attribute
+fn prediction_rows(predictions: &Predictions) -> Vec<Map<String, Value>> {
+    predictions
+        .data
+        .iter()
+        .map(|row| predictions.attribute_names.iter().cloned().zip(row.iter().cloned()).collect())
+        .collect()
+}
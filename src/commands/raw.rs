@@ -0,0 +1,73 @@
+use crate::commands;
+use crate::output;
+use reqwest::Method;
+use serde_json::json;
+use std::fs;
+use sym_rest_rc::{SymClient, SymError};
+
+pub async fn handle_raw(
+    method: String,
+    resource: String,
+    query: Vec<String>,
+    body: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+        .map_err(|_| SymError::Usage(format!("Invalid HTTP method '{}'", method)))?;
+
+    // Resource paths are relative to /symetry/rest/{cid} unless given in full
+    let client = SymClient::from_config()?;
+    let resource = if resource.starts_with("/symetry/") {
+        resource
+    } else {
+        client.resource(&format!("/{}", resource.trim_start_matches('/')))
+    };
+
+    // Query parameters are joined in the order given, and signed as sent
+    for q in &query {
+        if !q.contains('=') {
+            return Err(SymError::Usage(format!("Invalid query parameter '{}': expected key=value", q)).into());
+        }
+    }
+    let query = (!query.is_empty()).then(|| query.join("&"));
+
+    // Request body, inline or read from a file with @path
+    let body = match body {
+        Some(b) => match b.strip_prefix('@') {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .map_err(|e| SymError::Io(format!("Failed to read body file {}: {}", path, e)))?,
+            ),
+            None => Some(b),
+        },
+        None => None,
+    };
+
+    match &query {
+        Some(q) => msg!("{} {}?{}", method, resource, q),
+        None => msg!("{} {}", method, resource),
+    }
+
+    let Some(response) = commands::send(client.send(method.clone(), &resource, query.as_deref(), body)).await? else {
+        return Ok(());
+    };
+
+    msg!("Status: {}", response.status);
+    if let Some(jid) = &response.job_id {
+        msg!("Job ID: {}", jid);
+    }
+
+    let result = json!({
+        "method": method.as_str(),
+        "resource": resource,
+        "query": query,
+        "status": response.status.as_u16(),
+        "jobId": response.job_id,
+        "response": response.body_value(),
+    });
+    output::emit_with_text(&result, &format!("{}\n", response.body))?;
+
+    if !response.is_success() {
+        return Err(SymError::from_response(&response).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use crate::cli::Cli;
+use crate::output::{self, OutputFormat};
+use clap::{CommandFactory, Parser};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Instant;
+use sym_rest_rc::{config, SymError};
+
+/// Pipeline file
+///
+/// ```toml
+/// [vars]
+/// project = "iris"
+///
+/// [[steps]]
+/// id = "build"
+/// command = "model build"
+/// project = "${vars.project}"
+/// name = "m1"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Pipeline {
+    /// Values referenced as `${vars.<name>}`
+    #[serde(default)]
+    vars: toml::Table,
+    steps: Vec<toml::Table>,
+}
+
+/// A step of the pipeline: a command and its flags
+struct Step {
+    /// Name used to reference the results of the step, `step<N>` if not set
+    id: String,
+    /// Command line words, e.g. "model build"
+    command: String,
+    /// Flags without the leading `--`; underscores become dashes
+    flags: toml::Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum StepStatus {
+    Ok,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of a step, for the summary
+#[derive(Serialize)]
+struct StepSummary {
+    id: String,
+    command: String,
+    status: StepStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seconds: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn handle_run(file: String) -> Result<(), Box<dyn std::error::Error>> {
+    let (vars, steps) = load(&file)?;
+    msg!("Running pipeline {} ({} steps)", file, steps.len());
+
+    // Values that steps can reference: the variables, then the result of every step run so far
+    let mut context: BTreeMap<String, Value> = BTreeMap::new();
+    context.insert("vars".to_string(), serde_json::to_value(&vars)?);
+
+    let mut summary = Vec::new();
+    let mut failure = None;
+    // A dry run prints the request of the first step only, since the next steps may use its result
+    let dry_run = config::Config::dry_run().is_some();
+
+    for (i, step) in steps.iter().enumerate() {
+        let mut entry = StepSummary {
+            id: step.id.clone(),
+            command: step.command.clone(),
+            status: StepStatus::Skipped,
+            seconds: None,
+            error: None,
+        };
+        if failure.is_some() || (dry_run && i > 0) {
+            summary.push(entry);
+            continue;
+        }
+
+        msg!();
+        msg!("==> Step {}/{}: {} ({})", i + 1, steps.len(), step.id, step.command);
+        let started = Instant::now();
+        let result = run_step(step, &context).await;
+        entry.seconds = Some(started.elapsed().as_secs_f64());
+
+        match result {
+            Ok(value) => {
+                entry.status = StepStatus::Ok;
+                context.insert(step.id.clone(), value);
+            }
+            Err(e) => {
+                entry.status = StepStatus::Failed;
+                entry.error = Some(e.to_string());
+                failure = Some((step.id.clone(), e));
+            }
+        }
+        summary.push(entry);
+    }
+
+    // Summary
+    msg!();
+    msg!("Pipeline summary:");
+    for s in &summary {
+        let status = match s.status {
+            StepStatus::Ok => "OK",
+            StepStatus::Failed => "FAILED",
+            StepStatus::Skipped => "SKIPPED",
+        };
+        let detail = match (&s.error, s.seconds) {
+            (Some(e), _) => e.lines().next().unwrap_or_default().to_string(),
+            (None, Some(t)) => format!("{:.1}s", t),
+            (None, None) => String::new(),
+        };
+        let line = format!("  {:<8} {:<16} {:<20} {}", status, s.id, s.command, detail);
+        msg!("{}", line.trim_end());
+    }
+
+    if dry_run && steps.len() > 1 {
+        msg!("Dry run: the steps after the first one were not run");
+    }
+
+    context.remove("vars");
+    output::emit(&json!({
+        "pipeline": file,
+        "dryRun": dry_run,
+        "passed": failure.is_none(),
+        "steps": summary,
+        "results": context,
+    }))?;
+
+    // The error of the failed step is returned as is, so its exit code is kept
+    match failure {
+        Some((id, e)) => {
+            msg!("Pipeline stopped at step '{}'", id);
+            Err(e)
+        }
+        None => {
+            msg!("Pipeline finished successfully");
+            Ok(())
+        }
+    }
+}
+
+/// Read and check a pipeline file
+fn load(path: &str) -> Result<(toml::Table, Vec<Step>), Box<dyn std::error::Error>> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| SymError::Io(format!("Failed to read pipeline {}: {}", path, e)))?;
+    let pipeline: Pipeline = toml::from_str(&data)
+        .map_err(|e| SymError::Usage(format!("Invalid pipeline {}: {}", path, e)))?;
+    if pipeline.steps.is_empty() {
+        return Err(SymError::Usage(format!("Pipeline {} has no steps", path)).into());
+    }
+
+    let mut steps: Vec<Step> = Vec::new();
+    for (i, mut flags) in pipeline.steps.into_iter().enumerate() {
+        let id = match flags.remove("id") {
+            None => format!("step{}", i + 1),
+            Some(toml::Value::String(id)) => id,
+            Some(_) => return Err(SymError::Usage(format!("Step {}: id must be a string", i + 1)).into()),
+        };
+        if id == "vars" || id.contains('.') || steps.iter().any(|s| s.id == id) {
+            return Err(SymError::Usage(format!(
+                "Step {}: id '{}' is reserved, contains a dot or is used twice",
+                i + 1,
+                id
+            )).into());
+        }
+        let command = match flags.remove("command") {
+            Some(toml::Value::String(c)) if !c.trim().is_empty() => c,
+            _ => {
+                return Err(SymError::Usage(format!(
+                    "Step '{}': missing command (e.g. command = \"model build\")",
+                    id
+                )).into())
+            }
+        };
+        // Predictions streamed to stdout are not part of the result, so they would be lost
+        if command.split_whitespace().eq(["model", "predict"]) && !flags.contains_key("out") {
+            return Err(SymError::Usage(format!(
+                "Step '{}': model predict needs out = \"<file>\" in a pipeline",
+                id
+            )).into());
+        }
+        steps.push(Step { id, command, flags });
+    }
+
+    Ok((pipeline.vars, steps))
+}
+
+/// Run one step and return its result, as printed by `--output=json`
+async fn run_step(step: &Step, context: &BTreeMap<String, Value>) -> Result<Value, Box<dyn std::error::Error>> {
+    let args = step_args(step, context)?;
+    let cli = Cli::try_parse_from(&args).map_err(|e| SymError::Usage(e.to_string().trim_end().to_string()))?;
+    if let Some(flag) = global_flag(&cli) {
+        return Err(SymError::Usage(format!("{} is a global option and cannot be set in a step", flag)).into());
+    }
+
+    output::start_capture();
+    let result = crate::run_command(cli.command).await;
+    let value = output::take_capture();
+    result?;
+    Ok(value.unwrap_or(Value::Null))
+}
+
+/// Build the command line of a step, resolving references to earlier steps
+fn step_args(step: &Step, context: &BTreeMap<String, Value>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut args = vec!["sym-rest-rc".to_string()];
+    args.extend(step.command.split_whitespace().map(str::to_string));
+
+    for (key, value) in &step.flags {
+        let flag = format!("--{}", key.replace('_', "-"));
+        match value {
+            toml::Value::Boolean(true) => args.push(flag),
+            toml::Value::Boolean(false) => {}
+            // Lists are passed comma-separated, like on the command line
+            toml::Value::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|v| flag_value(key, v, context))
+                    .collect::<Result<Vec<_>, _>>()?;
+                args.push(format!("{}={}", flag, items.join(",")));
+            }
+            v => args.push(format!("{}={}", flag, flag_value(key, v, context)?)),
+        }
+    }
+
+    // Wait for asynchronous jobs, so the next step sees their result
+    if !step.flags.contains_key("wait") && has_flag(&step.command, "wait") {
+        args.push("--wait".to_string());
+    }
+    Ok(args)
+}
+
+/// Convert a scalar flag value to text
+fn flag_value(key: &str, value: &toml::Value, context: &BTreeMap<String, Value>) -> Result<String, Box<dyn std::error::Error>> {
+    match value {
+        toml::Value::String(s) => interpolate(s, context),
+        toml::Value::Integer(n) => Ok(n.to_string()),
+        toml::Value::Float(n) => Ok(n.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(SymError::Usage(format!("Unsupported value for '{}'", key)).into()),
+    }
+}
+
+/// Check if a command accepts a flag
+fn has_flag(command: &str, flag: &str) -> bool {
+    command
+        .split_whitespace()
+        .try_fold(Cli::command(), |cmd, word| cmd.find_subcommand(word).cloned())
+        .is_some_and(|cmd| cmd.get_arguments().any(|a| a.get_id() == flag))
+}
+
+/// Get the first global flag set to something other than its default
+/// Global flags apply to the whole run, so a step cannot change them
+fn global_flag(cli: &Cli) -> Option<&'static str> {
+    [
+        ("--config", cli.config.is_some()),
+        ("--profile", cli.profile.is_some()),
+        ("--host", cli.host.is_some()),
+        ("--port", cli.port.is_some()),
+        ("--user", cli.user.is_some()),
+        ("--dry-run", cli.dry_run),
+        ("--show-secret", cli.show_secret),
+        ("--output", cli.output != OutputFormat::Text),
+        ("--retries", cli.retries.is_some()),
+        ("--retry-non-idempotent", cli.retry_non_idempotent),
+        ("--connect-timeout", cli.connect_timeout.is_some()),
+        ("--request-timeout", cli.request_timeout.is_some()),
+        ("--ws-idle-timeout", cli.ws_idle_timeout.is_some()),
+    ]
+    .into_iter()
+    .find(|(_, set)| *set)
+    .map(|(flag, _)| flag)
+}
+
+/// Replace `${<step>.<field>}` and `${vars.<name>}` references in a value
+fn interpolate(text: &str, context: &BTreeMap<String, Value>) -> Result<String, Box<dyn std::error::Error>> {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| SymError::Usage(format!("Unterminated reference in '{}'", text)))?;
+        out.push_str(&resolve(&rest[start + 2..start + end], context)?);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Look up a reference: a step id or `vars`, then field names or array indexes separated by dots
+fn resolve(reference: &str, context: &BTreeMap<String, Value>) -> Result<String, Box<dyn std::error::Error>> {
+    let mut parts = reference.split('.');
+    let root = parts.next().unwrap_or_default();
+    let mut value = context
+        .get(root)
+        .ok_or_else(|| SymError::Usage(format!("${{{}}}: no earlier step '{}'", reference, root)))?;
+
+    for part in parts {
+        value = match value {
+            Value::Object(map) => map.get(part),
+            Value::Array(items) => part.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+        .ok_or_else(|| SymError::Usage(format!("${{{}}}: no field '{}'", reference, part)))?;
+    }
+
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Null => Err(SymError::Usage(format!("${{{}}} is not set", reference)).into()),
+        other => Ok(other.to_string()),
+    }
+}
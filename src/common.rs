@@ -1,3 +1,4 @@
+use crate::error::SymError;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 
@@ -13,7 +14,7 @@ pub struct DataFrame {
 }
 
 /// MLContext structure used for build and autoselect operations
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct MLContext {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,6 +25,7 @@ pub struct MLContext {
     pub input_attribute_names: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub target_attribute_names: Option<Vec<String>>,
+    #[serde(default)]
     pub extra_parameters: HashMap<String, String>,
 }
 
@@ -41,7 +43,7 @@ pub fn parse_int_list_as_strings(s: &str) -> Result<Vec<String>, Box<dyn std::er
             item.trim()
                 .parse::<i32>()
                 .map(|i| i.to_string())
-                .map_err(|e| format!("Failed to parse '{}' as integer: {}", item.trim(), e).into())
+                .map_err(|e| SymError::Usage(format!("Failed to parse '{}' as integer: {}", item.trim(), e)).into())
         })
         .collect()
 }
@@ -52,3 +54,20 @@ pub fn parse_string_list(s: &str) -> Vec<String> {
         .map(|item| item.trim().to_string())
         .collect()
 }
+
+/// Helper function to parse comma-separated key=value pairs
+/// The value is everything after the first `=`, so it may contain `=` itself
+pub fn parse_key_value_list(s: &str) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let mut map = HashMap::new();
+    for pair in s.split(',') {
+        match pair.trim().split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                map.insert(key.trim().to_string(), value.to_string());
+            }
+            _ => {
+                return Err(SymError::Usage(format!("Invalid parameter '{}': expected key=value", pair.trim())).into())
+            }
+        }
+    }
+    Ok(map)
+}
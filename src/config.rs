@@ -1,28 +1,186 @@
+use crate::client::{DryRun, RetryPolicy};
+use crate::connection::{Timeouts, TlsSettings};
+use std::collections::BTreeMap;
 use std::fs;
 use std::sync::OnceLock;
 use std::path::PathBuf;
+use std::time::Duration;
 use serde::Deserialize;
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
+static DRY_RUN: OnceLock<DryRun> = OnceLock::new();
 
+/// Returned by `Config::auto_load` when no configuration file was read
+pub const NO_FILE: &str = "environment variables and flags (no configuration file)";
+
+/// Connection settings; used for the top level of the file and for each `[profiles.<name>]` section
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct Profile {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub secretkey: Option<String>,
+    /// File holding the secret key, instead of `secretkey`
+    pub secretkey_file: Option<String>,
+    /// Command printing the secret key on stdout, instead of `secretkey`
+    pub secretkey_command: Option<String>,
+    pub use_ws_for_learn: Option<bool>,
+    pub use_ws_for_predit: Option<bool>,
+    /// Use https:// and wss:// to reach the server
+    pub tls: Option<bool>,
+    /// PEM file with additional CA certificates to trust
+    pub ca_cert: Option<String>,
+    /// PEM file with a client certificate
+    pub client_cert: Option<String>,
+    /// PEM file with the PKCS#8 key of the client certificate
+    pub client_key: Option<String>,
+    /// Accept any server certificate (test servers only)
+    pub insecure_skip_verify: Option<bool>,
+    /// Number of retries after a network error or a retryable status
+    pub retries: Option<u32>,
+    /// Delay before the first retry, in milliseconds; doubles after every retry
+    pub retry_backoff_ms: Option<u64>,
+    /// HTTP status codes that are retried
+    pub retry_statuses: Option<Vec<u16>>,
+    /// Also retry calls that are not idempotent (learn, project create, build...)
+    pub retry_non_idempotent: Option<bool>,
+    /// Seconds allowed to connect to the server; 0 means no limit
+    pub connect_timeout: Option<u64>,
+    /// Seconds allowed for a whole REST request; 0 means no limit
+    pub request_timeout: Option<u64>,
+    /// Seconds allowed without a message on a WebSocket; 0 means no limit
+    pub ws_idle_timeout: Option<u64>,
+}
+
+impl Profile {
+    /// Read the settings set in `SML_<SETTING>` environment variables, e.g. SML_HOST or SML_RETRIES
+    /// Empty variables are ignored. Returns the settings and the names of the variables used.
+    /// The secret key itself is read from SML_SK, see `Config::secretkey`.
+    pub fn from_env() -> Result<(Profile, Vec<String>), ConfigError> {
+        let mut used = Vec::new();
+        let mut var = |name: &str| -> Option<String> {
+            let value = std::env::var(name).ok().filter(|v| !v.trim().is_empty())?;
+            used.push(name.to_string());
+            Some(value.trim().to_string())
+        };
+
+        let profile = Profile {
+            host: var("SML_HOST"),
+            port: parse_env("SML_PORT", var("SML_PORT"))?,
+            user: var("SML_USER"),
+            secretkey: None,
+            secretkey_file: var("SML_SECRETKEY_FILE"),
+            secretkey_command: var("SML_SECRETKEY_COMMAND"),
+            use_ws_for_learn: parse_env_bool("SML_USE_WS_FOR_LEARN", var("SML_USE_WS_FOR_LEARN"))?,
+            use_ws_for_predit: parse_env_bool("SML_USE_WS_FOR_PREDIT", var("SML_USE_WS_FOR_PREDIT"))?,
+            tls: parse_env_bool("SML_TLS", var("SML_TLS"))?,
+            ca_cert: var("SML_CA_CERT"),
+            client_cert: var("SML_CLIENT_CERT"),
+            client_key: var("SML_CLIENT_KEY"),
+            insecure_skip_verify: parse_env_bool("SML_INSECURE_SKIP_VERIFY", var("SML_INSECURE_SKIP_VERIFY"))?,
+            retries: parse_env("SML_RETRIES", var("SML_RETRIES"))?,
+            retry_backoff_ms: parse_env("SML_RETRY_BACKOFF_MS", var("SML_RETRY_BACKOFF_MS"))?,
+            // Comma-separated, e.g. "502,503,504"
+            retry_statuses: var("SML_RETRY_STATUSES")
+                .map(|list| {
+                    list.split(',')
+                        .map(|s| s.trim().parse::<u16>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| ConfigError::InvalidEnv("SML_RETRY_STATUSES".to_string(), list.clone()))
+                })
+                .transpose()?,
+            retry_non_idempotent: parse_env_bool("SML_RETRY_NON_IDEMPOTENT", var("SML_RETRY_NON_IDEMPOTENT"))?,
+            connect_timeout: parse_env("SML_CONNECT_TIMEOUT", var("SML_CONNECT_TIMEOUT"))?,
+            request_timeout: parse_env("SML_REQUEST_TIMEOUT", var("SML_REQUEST_TIMEOUT"))?,
+            ws_idle_timeout: parse_env("SML_WS_IDLE_TIMEOUT", var("SML_WS_IDLE_TIMEOUT"))?,
+        };
+        Ok((profile, used))
+    }
+
+    /// Check if any of the secret key settings is set
+    fn has_secret_key(&self) -> bool {
+        self.secretkey.is_some() || self.secretkey_file.is_some() || self.secretkey_command.is_some()
+    }
+
+    /// Overlay the values set in `other` on top of this profile
+    fn merge(&self, other: &Profile) -> Profile {
+        // The three secret key settings are one choice: a profile that sets any of
+        // them replaces the key source of the top level
+        let secret = if other.has_secret_key() { other } else { self };
+
+        Profile {
+            host: other.host.clone().or_else(|| self.host.clone()),
+            port: other.port.or(self.port),
+            user: other.user.clone().or_else(|| self.user.clone()),
+            secretkey: secret.secretkey.clone(),
+            secretkey_file: secret.secretkey_file.clone(),
+            secretkey_command: secret.secretkey_command.clone(),
+            use_ws_for_learn: other.use_ws_for_learn.or(self.use_ws_for_learn),
+            use_ws_for_predit: other.use_ws_for_predit.or(self.use_ws_for_predit),
+            tls: other.tls.or(self.tls),
+            ca_cert: other.ca_cert.clone().or_else(|| self.ca_cert.clone()),
+            client_cert: other.client_cert.clone().or_else(|| self.client_cert.clone()),
+            client_key: other.client_key.clone().or_else(|| self.client_key.clone()),
+            insecure_skip_verify: other.insecure_skip_verify.or(self.insecure_skip_verify),
+            retries: other.retries.or(self.retries),
+            retry_backoff_ms: other.retry_backoff_ms.or(self.retry_backoff_ms),
+            retry_statuses: other.retry_statuses.clone().or_else(|| self.retry_statuses.clone()),
+            retry_non_idempotent: other.retry_non_idempotent.or(self.retry_non_idempotent),
+            connect_timeout: other.connect_timeout.or(self.connect_timeout),
+            request_timeout: other.request_timeout.or(self.request_timeout),
+            ws_idle_timeout: other.ws_idle_timeout.or(self.ws_idle_timeout),
+        }
+    }
+}
+
+/// Layout of the TOML configuration file
 #[derive(Debug, Deserialize)]
+struct ConfigFile {
+    #[serde(flatten)]
+    base: Profile,
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug)]
 pub struct Config {
     pub host: String,
     pub port: u16,
     pub user: String,
     pub secretkey: String,
+    /// Where the secret key was read from
+    pub secretkey_source: String,
     pub use_ws_for_learn: bool,
     pub use_ws_for_predit: bool,
+    pub tls: TlsSettings,
+    pub retry: RetryPolicy,
+    pub timeouts: Timeouts,
+    /// Name of the selected profile, None when the top-level settings are used
+    pub profile: Option<String>,
+    /// All profiles defined in the file
+    pub profiles: BTreeMap<String, Profile>,
+    /// Problems found while loading that do not prevent running commands
+    pub warnings: Vec<String>,
+    /// `SML_*` environment variables that override settings
+    pub env_vars: Vec<String>,
 }
 
 impl Config {
     /// Load configuration from a TOML file
-    pub fn load(path: &str) -> Result<(), ConfigError> {
+    ///
+    /// The selected profile is, in order: `profile`, the SML_PROFILE environment
+    /// variable, then `default_profile` from the file. Settings are merged, from
+    /// lowest to highest precedence:
+    /// 1. the top level of the file
+    /// 2. the selected profile
+    /// 3. `SML_*` environment variables (see `Profile::from_env`)
+    /// 4. `overrides`, from command line flags
+    pub fn load(path: &str, profile: Option<String>, overrides: &Profile) -> Result<(), ConfigError> {
         let data = fs::read_to_string(path)
             .map_err(|e| ConfigError::ReadError(e.to_string()))?;
 
-        let parsed: Config = toml::from_str(&data)
-            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+        let parsed = Self::parse(&data, profile, overrides)?;
 
         CONFIG.set(parsed)
             .map_err(|_| ConfigError::AlreadyInitialized)?;
@@ -30,20 +188,114 @@ impl Config {
         Ok(())
     }
 
+    /// Parse configuration from TOML and resolve the selected profile
+    fn parse(data: &str, profile: Option<String>, overrides: &Profile) -> Result<Config, ConfigError> {
+        let file: ConfigFile = toml::from_str(data)
+            .map_err(|e| ConfigError::ParseError(e.to_string()))?;
+
+        let selected = profile
+            .or_else(|| std::env::var("SML_PROFILE").ok())
+            .or(file.default_profile);
+
+        let settings = match &selected {
+            Some(name) => {
+                let p = file.profiles.get(name).ok_or_else(|| {
+                    ConfigError::ProfileNotFound(
+                        name.clone(),
+                        file.profiles.keys().cloned().collect::<Vec<_>>().join(", "),
+                    )
+                })?;
+                file.base.merge(p)
+            }
+            None => file.base.clone(),
+        };
+        let (env, env_vars) = Profile::from_env()?;
+        let settings = settings.merge(&env).merge(overrides);
+
+        // SML_SK takes precedence, so the file or helper is not used when it is set
+        let mut warnings = Vec::new();
+        let (secretkey, secretkey_source) = if std::env::var("SML_SK").is_ok() {
+            (String::new(), "SML_SK environment variable".to_string())
+        } else {
+            let (key, source, warning) = resolve_secret_key(&settings)?;
+            warnings.extend(warning);
+            (key, source)
+        };
+
+        let default_retry = RetryPolicy::default();
+        let default_timeouts = Timeouts::default();
+        // A timeout of 0 seconds disables the limit
+        let timeout = |value: Option<u64>, default: Option<Duration>| match value {
+            Some(0) => None,
+            Some(secs) => Some(Duration::from_secs(secs)),
+            None => default,
+        };
+
+        let missing = |field: &str| ConfigError::MissingField(field.to_string(), selected.clone());
+
+        Ok(Config {
+            host: settings.host.ok_or_else(|| missing("host"))?,
+            port: settings.port.ok_or_else(|| missing("port"))?,
+            user: settings.user.ok_or_else(|| missing("user"))?,
+            secretkey,
+            secretkey_source,
+            use_ws_for_learn: settings.use_ws_for_learn.unwrap_or(false),
+            use_ws_for_predit: settings.use_ws_for_predit.unwrap_or(false),
+            tls: TlsSettings {
+                enabled: settings.tls.unwrap_or(false),
+                ca_cert: settings.ca_cert,
+                client_cert: settings.client_cert,
+                client_key: settings.client_key,
+                insecure_skip_verify: settings.insecure_skip_verify.unwrap_or(false),
+            },
+            retry: RetryPolicy {
+                max_retries: settings.retries.unwrap_or(default_retry.max_retries),
+                initial_backoff: settings
+                    .retry_backoff_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(default_retry.initial_backoff),
+                retry_statuses: settings.retry_statuses.unwrap_or(default_retry.retry_statuses),
+                retry_non_idempotent: settings.retry_non_idempotent.unwrap_or(false),
+                ..default_retry
+            },
+            timeouts: Timeouts {
+                connect: timeout(settings.connect_timeout, default_timeouts.connect),
+                request: timeout(settings.request_timeout, default_timeouts.request),
+                ws_idle: timeout(settings.ws_idle_timeout, default_timeouts.ws_idle),
+            },
+            profile: selected,
+            profiles: file.profiles,
+            warnings,
+            env_vars,
+        })
+    }
+
     /// Auto-load configuration from various sources with priority:
     /// 1. Provided config_path (from --config flag)
     /// 2. SML_CONFIG_FILE environment variable
     /// 3. Default locations: ./rc.conf, ~/.config/sym-rest-rc/config.toml, ~/.sym-rest-rc/config.toml
-    pub fn auto_load(config_path: Option<String>) -> Result<String, ConfigError> {
+    ///
+    /// Only the first file found is read. Without a file, the settings come from
+    /// the environment variables and `overrides` alone, which then must at least
+    /// set the host, port and user.
+    ///
+    /// `profile` selects a `[profiles.<name>]` section (from the --profile flag), and
+    /// `overrides` holds the settings given as command line flags.
+    /// Returns the path of the file, or a description of where the settings came from.
+    pub fn auto_load(
+        config_path: Option<String>,
+        profile: Option<String>,
+        overrides: &Profile,
+    ) -> Result<String, ConfigError> {
         // Priority 1: Explicit config path from flag
         if let Some(path) = config_path {
-            Self::load(&path)?;
+            Self::load(&path, profile, overrides)?;
             return Ok(path);
         }
 
         // Priority 2: Environment variable
         if let Ok(env_path) = std::env::var("SML_CONFIG_FILE") {
-            Self::load(&env_path)?;
+            Self::load(&env_path, profile, overrides)?;
             return Ok(env_path);
         }
 
@@ -52,22 +304,32 @@ impl Config {
         for path in &default_paths {
             if path.exists() {
                 let path_str = path.to_string_lossy().to_string();
-                Self::load(&path_str)?;
+                Self::load(&path_str, profile, overrides)?;
                 return Ok(path_str);
             }
         }
 
-        // No config found
-        Err(ConfigError::NotFound(format!(
-            "No configuration file found. Tried:\n  \
-            - --config flag\n  \
-            - SML_CONFIG_FILE environment variable\n  \
-            - Default locations: {}",
-            default_paths.iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )))
+        // No config file: environment variables and flags only
+        match Self::parse("", profile, overrides) {
+            Ok(parsed) => {
+                CONFIG.set(parsed)
+                    .map_err(|_| ConfigError::AlreadyInitialized)?;
+                Ok(NO_FILE.to_string())
+            }
+            Err(ConfigError::MissingField(field, _)) => Err(ConfigError::NotFound(format!(
+                "No configuration file found and '{}' is not set. Tried:\n  \
+                - --config flag\n  \
+                - SML_CONFIG_FILE environment variable\n  \
+                - Default locations: {}\n\
+                Or set SML_HOST, SML_PORT, SML_USER and SML_SK (or --host, --port and --user)",
+                field,
+                default_paths.iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+            Err(e) => Err(e),
+        }
     }
 
     /// Get list of default configuration file paths
@@ -108,6 +370,25 @@ impl Config {
             .unwrap_or_else(|_| Self::get().secretkey.clone())
     }
 
+    /// Describe where the secret key comes from, for display
+    pub fn secretkey_source() -> String {
+        if std::env::var("SML_SK").is_ok() {
+            "SML_SK environment variable".to_string()
+        } else {
+            Self::get().secretkey_source.clone()
+        }
+    }
+
+    /// Get the `SML_*` environment variables that override settings
+    pub fn env_vars() -> &'static [String] {
+        &Self::get().env_vars
+    }
+
+    /// Get the warnings found while loading the configuration
+    pub fn warnings() -> &'static [String] {
+        &Self::get().warnings
+    }
+
     /// Check if WebSocket should be used for learn operations
     pub fn use_ws_for_learn() -> bool {
         Self::get().use_ws_for_learn
@@ -118,6 +399,41 @@ impl Config {
         Self::get().use_ws_for_predit
     }
 
+    /// Print requests instead of sending them, for the rest of the process (see `DryRun`)
+    pub fn enable_dry_run(dry_run: DryRun) {
+        let _ = DRY_RUN.set(dry_run);
+    }
+
+    /// Check if requests are printed instead of sent
+    pub fn dry_run() -> Option<DryRun> {
+        DRY_RUN.get().copied()
+    }
+
+    /// Get the TLS settings from the loaded configuration
+    pub fn tls() -> &'static TlsSettings {
+        &Self::get().tls
+    }
+
+    /// Get the retry policy for REST requests
+    pub fn retry() -> &'static RetryPolicy {
+        &Self::get().retry
+    }
+
+    /// Get the connect, request and WebSocket idle timeouts
+    pub fn timeouts() -> &'static Timeouts {
+        &Self::get().timeouts
+    }
+
+    /// Get the name of the selected profile
+    pub fn profile() -> Option<&'static str> {
+        Self::get().profile.as_deref()
+    }
+
+    /// Get all profiles defined in the configuration file
+    pub fn profiles() -> &'static BTreeMap<String, Profile> {
+        &Self::get().profiles
+    }
+
     /// Get reference to the loaded configuration
     fn get() -> &'static Config {
         CONFIG.get().expect("Config not loaded")
@@ -137,4 +453,137 @@ pub enum ConfigError {
 
     #[error("{0}")]
     NotFound(String),
+
+    #[error("Profile '{0}' not found in config (available: {1})")]
+    ProfileNotFound(String, String),
+
+    #[error("Missing '{0}' in config{}", .1.as_ref().map(|p| format!(" (profile '{}')", p)).unwrap_or_default())]
+    MissingField(String, Option<String>),
+
+    #[error("Failed to read secret key: {0}")]
+    SecretKeyError(String),
+
+    #[error("Invalid value for {0}: '{1}'")]
+    InvalidEnv(String, String),
+}
+
+/// Parse the value of an environment variable
+fn parse_env<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<Option<T>, ConfigError> {
+    value
+        .map(|v| v.parse().map_err(|_| ConfigError::InvalidEnv(name.to_string(), v)))
+        .transpose()
+}
+
+/// Parse a boolean environment variable: true/false, 1/0 or yes/no
+fn parse_env_bool(name: &str, value: Option<String>) -> Result<Option<bool>, ConfigError> {
+    value
+        .map(|v| match v.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(true),
+            "false" | "0" | "no" => Ok(false),
+            _ => Err(ConfigError::InvalidEnv(name.to_string(), v)),
+        })
+        .transpose()
+}
+
+/// Hide a secret, keeping only its last 4 characters when it is long enough
+pub fn redact(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if secret.is_empty() {
+        "(not set)".to_string()
+    } else if chars.len() <= 12 {
+        "********".to_string()
+    } else {
+        format!("********{}", chars[chars.len() - 4..].iter().collect::<String>())
+    }
+}
+
+/// Resolve the secret key from `secretkey`, `secretkey_file` or `secretkey_command`
+/// Returns the key, a description of its source, and any warning
+fn resolve_secret_key(settings: &Profile) -> Result<(String, String, Option<String>), ConfigError> {
+    if let Some(key) = &settings.secretkey {
+        return Ok((key.clone(), "config file".to_string(), None));
+    }
+
+    if let Some(path) = &settings.secretkey_file {
+        let path = expand_home(path);
+        let key = fs::read_to_string(&path)
+            .map_err(|e| ConfigError::SecretKeyError(format!("{}: {}", path.display(), e)))?;
+        let warning = check_permissions(&path);
+        return Ok((
+            key.trim().to_string(),
+            format!("secretkey_file {}", path.display()),
+            warning,
+        ));
+    }
+
+    if let Some(command) = &settings.secretkey_command {
+        return Ok((
+            run_secret_command(command)?,
+            format!("secretkey_command `{}`", command),
+            None,
+        ));
+    }
+
+    Ok((String::new(), "not set".to_string(), None))
+}
+
+/// Expand a leading `~/` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Warn when the secret key file can be read by the group or by other users
+#[cfg(unix)]
+fn check_permissions(path: &std::path::Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    (mode & 0o077 != 0).then(|| {
+        format!(
+            "secret key file {} is readable by group or others (mode {:o}); run: chmod 600 {}",
+            path.display(),
+            mode,
+            path.display()
+        )
+    })
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &std::path::Path) -> Option<String> {
+    None
+}
+
+/// Run a helper command and use its trimmed stdout as the secret key
+/// stdin and stderr are inherited so the helper can prompt for a passphrase
+fn run_secret_command(command: &str) -> Result<String, ConfigError> {
+    let mut shell = if cfg!(windows) {
+        let mut c = std::process::Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = std::process::Command::new("sh");
+        c.arg("-c");
+        c
+    };
+
+    let output = shell
+        .arg(command)
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .map_err(|e| ConfigError::SecretKeyError(format!("failed to run `{}`: {}", command, e)))?;
+
+    if !output.status.success() {
+        return Err(ConfigError::SecretKeyError(format!(
+            "`{}` exited with {}",
+            command, output.status
+        )));
+    }
+
+    let key = String::from_utf8(output.stdout)
+        .map_err(|_| ConfigError::SecretKeyError(format!("`{}` did not print UTF-8", command)))?;
+    Ok(key.trim().to_string())
 }
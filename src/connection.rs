@@ -0,0 +1,195 @@
+use crate::config::ConfigError;
+use crate::error::SymError;
+use std::fs;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream};
+
+/// WebSocket stream returned by `connect_ws`
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// PEM-encoded client certificate and private key
+type PemIdentity = (Vec<u8>, Vec<u8>);
+
+/// Timeouts shared by the REST and WebSocket connections
+/// None means no limit
+#[derive(Debug, Clone)]
+pub struct Timeouts {
+    /// Time allowed to open a TCP/TLS connection or a WebSocket
+    pub connect: Option<Duration>,
+    /// Time allowed for a whole REST request, from sending it to reading the response
+    pub request: Option<Duration>,
+    /// Time allowed without any message from the server on a WebSocket
+    pub ws_idle: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            connect: Some(Duration::from_secs(10)),
+            request: Some(Duration::from_secs(300)),
+            ws_idle: Some(Duration::from_secs(300)),
+        }
+    }
+}
+
+/// Format a timeout setting for error messages, e.g. "connect_timeout (10s)"
+pub fn describe_timeout(name: &str, value: Option<Duration>) -> String {
+    match value {
+        Some(d) => format!("{} ({}s)", name, d.as_secs_f64()),
+        None => name.to_string(),
+    }
+}
+
+/// TLS settings shared by the REST and WebSocket connections
+#[derive(Debug, Clone, Default)]
+pub struct TlsSettings {
+    /// Use https:// and wss:// instead of http:// and ws://
+    pub enabled: bool,
+    /// PEM file with additional CA certificates to trust
+    pub ca_cert: Option<String>,
+    /// PEM file with the client certificate (requires `client_key`)
+    pub client_cert: Option<String>,
+    /// PEM file with the PKCS#8 private key of the client certificate
+    pub client_key: Option<String>,
+    /// Accept any server certificate and host name (test servers only)
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsSettings {
+    /// Scheme used for REST requests
+    pub fn http_scheme(&self) -> &'static str {
+        if self.enabled { "https" } else { "http" }
+    }
+
+    /// Scheme used for WebSocket connections
+    pub fn ws_scheme(&self) -> &'static str {
+        if self.enabled { "wss" } else { "ws" }
+    }
+
+    /// Read the CA certificate file, if any
+    /// The file may hold several certificates, all of which are trusted
+    fn read_ca_cert(&self) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let Some(path) = &self.ca_cert else {
+            return Ok(None);
+        };
+        let pem = read_pem(path, "CA certificate")?;
+        if pem_certificates(&pem).is_empty() {
+            return Err(SymError::Io(format!("No certificate found in CA certificate {}", path)).into());
+        }
+        Ok(Some(pem))
+    }
+
+    /// Read the client certificate and key files, if any
+    fn read_identity(&self) -> Result<Option<PemIdentity>, Box<dyn std::error::Error>> {
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => Ok(Some((
+                read_pem(cert, "client certificate")?,
+                read_pem(key, "client key")?,
+            ))),
+            (None, None) => Ok(None),
+            (Some(_), None) => Err(ConfigError::MissingField("client_key".to_string(), None).into()),
+            (None, Some(_)) => Err(ConfigError::MissingField("client_cert".to_string(), None).into()),
+        }
+    }
+
+    /// Build the HTTP client used for REST requests
+    pub fn http_client(&self, timeouts: &Timeouts) -> Result<reqwest::Client, Box<dyn std::error::Error>> {
+        let mut builder = reqwest::Client::builder();
+
+        if let Some(t) = timeouts.connect {
+            builder = builder.connect_timeout(t);
+        }
+        if let Some(t) = timeouts.request {
+            builder = builder.timeout(t);
+        }
+
+        if let Some(pem) = self.read_ca_cert()? {
+            for cert in reqwest::Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some((cert, key)) = self.read_identity()? {
+            builder = builder.identity(reqwest::Identity::from_pkcs8_pem(&cert, &key)?);
+        }
+        if self.insecure_skip_verify {
+            builder = builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Build the TLS connector used for WebSocket connections
+    /// Returns None when TLS is disabled
+    pub fn ws_connector(&self) -> Result<Option<Connector>, Box<dyn std::error::Error>> {
+        if !self.enabled {
+            return Ok(None);
+        }
+
+        let mut builder = native_tls::TlsConnector::builder();
+
+        if let Some(pem) = self.read_ca_cert()? {
+            for block in pem_certificates(&pem) {
+                builder.add_root_certificate(native_tls::Certificate::from_pem(block)?);
+            }
+        }
+        if let Some((cert, key)) = self.read_identity()? {
+            builder.identity(native_tls::Identity::from_pkcs8(&cert, &key)?);
+        }
+        if self.insecure_skip_verify {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(Some(Connector::NativeTls(builder.build()?)))
+    }
+}
+
+/// Read a PEM file, naming it in the error message
+fn read_pem(path: &str, what: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    fs::read(path).map_err(|e| SymError::Io(format!("Failed to read {} {}: {}", what, path, e)).into())
+}
+
+/// Split a PEM bundle into its certificate blocks, ignoring any other content
+fn pem_certificates(pem: &[u8]) -> Vec<&[u8]> {
+    const BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    const END: &[u8] = b"-----END CERTIFICATE-----";
+    let find = |data: &[u8], needle: &[u8]| data.windows(needle.len()).position(|w| w == needle);
+
+    let mut blocks = Vec::new();
+    let mut rest = pem;
+    while let Some(start) = find(rest, BEGIN) {
+        let Some(len) = find(&rest[start..], END) else {
+            break;
+        };
+        let end = start + len + END.len();
+        blocks.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+    blocks
+}
+
+/// Open a WebSocket connection, using TLS for wss:// URLs
+/// Fails if the handshake does not complete within the connect timeout
+pub async fn connect_ws(
+    url: &str,
+    tls: &TlsSettings,
+    timeouts: &Timeouts,
+) -> Result<WsStream, Box<dyn std::error::Error>> {
+    let connector = tls.ws_connector()?;
+    let connect = connect_async_tls_with_config(url, None, false, connector);
+    let (ws_stream, _) = match timeouts.connect {
+        Some(t) => tokio::time::timeout(t, connect).await.map_err(|_| {
+            SymError::Network(format!(
+                "{} elapsed while opening WebSocket {}",
+                describe_timeout("connect_timeout", timeouts.connect),
+                url
+            ))
+        })??,
+        None => connect.await?,
+    };
+    Ok(ws_stream)
+}
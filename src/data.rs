@@ -0,0 +1,414 @@
+use crate::common::DataFrame;
+use crate::error::SymError;
+use crate::responses::Predictions;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+/// Options controlling how data files are parsed
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Field delimiter
+    pub delimiter: u8,
+    /// Whether the first record holds the attribute names
+    pub has_header: bool,
+    /// Attribute names for files without a header
+    pub columns: Option<Vec<String>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: b',',
+            has_header: true,
+            columns: None,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Build options from command line values
+    ///
+    /// # Arguments
+    /// * `delimiter` - A single ASCII character, or one of "tab", "comma", "semicolon", "pipe", "\t"
+    /// * `no_header` - The file has no header line
+    /// * `columns` - Comma-separated attribute names, required when `no_header` is set
+    pub fn new(
+        delimiter: &str,
+        no_header: bool,
+        columns: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let delimiter = parse_delimiter(delimiter)?;
+
+        let columns = columns.map(|c| {
+            c.split(',')
+                .map(|s| s.trim().to_string())
+                .collect::<Vec<String>>()
+        });
+
+        if no_header && columns.is_none() {
+            return Err(SymError::Usage("--no-header requires --columns with the attribute names".to_string()).into());
+        }
+
+        Ok(CsvOptions {
+            delimiter,
+            has_header: !no_header,
+            columns,
+        })
+    }
+}
+
+/// Parse a delimiter name or character
+fn parse_delimiter(s: &str) -> Result<u8, Box<dyn std::error::Error>> {
+    match s {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        "pipe" => Ok(b'|'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(SymError::Usage(format!(
+            "Invalid delimiter '{}': expected a single ASCII character or tab",
+            s
+        )).into()),
+    }
+}
+
+/// Reads an RFC 4180 CSV file as attribute names followed by batches of rows
+///
+/// Quoted fields, embedded delimiters and newlines, escaped quotes, CRLF line
+/// endings and a UTF-8 BOM are handled. Only one batch is held in memory at a
+/// time, so arbitrarily large files can be streamed to the server.
+pub struct CsvBatchReader<R: Read> {
+    reader: csv::Reader<R>,
+    attribute_names: Vec<String>,
+    batch_rows: usize,
+    normalize_numbers: bool,
+    total_bytes: Option<u64>,
+}
+
+impl CsvBatchReader<File> {
+    /// Open a CSV file and read its header
+    pub fn open(
+        path: &str,
+        batch_rows: usize,
+        options: &CsvOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_handle = File::open(path)
+            .map_err(|e| SymError::Io(format!("Failed to open {}: {}", path, e)))?;
+        let total_bytes = file_handle.metadata().ok().map(|m| m.len());
+        let mut reader = Self::new(file_handle, batch_rows, options)?;
+        reader.total_bytes = total_bytes;
+        Ok(reader)
+    }
+}
+
+impl<R: Read> CsvBatchReader<R> {
+    /// Read the attribute names and prepare to read batches of `batch_rows` rows
+    pub fn new(
+        reader: R,
+        batch_rows: usize,
+        options: &CsvOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(options.delimiter)
+            .has_headers(options.has_header)
+            .flexible(true)
+            .from_reader(reader);
+
+        let attribute_names: Vec<String> = match &options.columns {
+            Some(columns) if !options.has_header => columns.clone(),
+            _ => {
+                let headers = reader.headers()?;
+                if headers.is_empty() || (headers.len() == 1 && headers[0].is_empty()) {
+                    return Err(SymError::Csv("Empty CSV file".to_string()).into());
+                }
+                headers.iter().map(|s| s.to_string()).collect()
+            }
+        };
+
+        Ok(CsvBatchReader {
+            reader,
+            attribute_names,
+            batch_rows: batch_rows.max(1),
+            normalize_numbers: false,
+            total_bytes: None,
+        })
+    }
+
+    /// Rewrite whole decimal values as integers (e.g. "3.0" becomes "3")
+    pub fn normalize_numbers(mut self, normalize: bool) -> Self {
+        self.normalize_numbers = normalize;
+        self
+    }
+
+    /// Get the attribute names read from the header or given as columns
+    pub fn attribute_names(&self) -> &[String] {
+        &self.attribute_names
+    }
+
+    /// Get the fraction of the file consumed so far, if the file size is known
+    pub fn progress(&self) -> Option<f64> {
+        self.total_bytes
+            .filter(|&total| total > 0)
+            .map(|total| self.reader.position().byte() as f64 / total as f64)
+    }
+
+    /// Read the next batch of rows, skipping empty lines
+    /// Returns None once the end of the file is reached
+    pub fn next_batch(&mut self) -> Result<Option<Vec<Vec<String>>>, Box<dyn std::error::Error>> {
+        let mut data: Vec<Vec<String>> = Vec::new();
+        let mut record = csv::StringRecord::new();
+
+        while data.len() < self.batch_rows {
+            if !self.reader.read_record(&mut record)? {
+                break;
+            }
+            if record.len() == 1 && record[0].is_empty() {
+                continue; // Skip empty lines
+            }
+            if record.len() != self.attribute_names.len() {
+                let line = record.position().map(|p| p.line()).unwrap_or(0);
+                return Err(SymError::Csv(format!(
+                    "Line {} has {} fields, expected {}",
+                    line,
+                    record.len(),
+                    self.attribute_names.len()
+                )).into());
+            }
+            let row: Vec<String> = record
+                .iter()
+                .map(|s| {
+                    if self.normalize_numbers {
+                        normalize_number(s)
+                    } else {
+                        s.to_string()
+                    }
+                })
+                .collect();
+            data.push(row);
+        }
+
+        if data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(data))
+        }
+    }
+
+    /// Read all remaining rows
+    pub fn read_all(mut self) -> Result<Vec<Vec<String>>, Box<dyn std::error::Error>> {
+        self.batch_rows = usize::MAX;
+        Ok(self.next_batch()?.unwrap_or_default())
+    }
+}
+
+/// Clean up decimal values that should be integers
+pub fn normalize_number(value: &str) -> String {
+    if let Ok(val) = value.parse::<f64>() {
+        if val.fract() == 0.0 {
+            // It's a whole number, return as integer string
+            return format!("{}", val as i64);
+        }
+    }
+    value.to_string()
+}
+
+/// Output file written by `PredictionWriter`
+enum PredictionSink {
+    Csv(Box<csv::Writer<File>>),
+    JsonLines(BufWriter<File>),
+}
+
+/// Writes input rows joined with their predicted values
+///
+/// A `.csv` file gets the input columns followed by the prediction columns, and
+/// a `.jsonl` file gets one JSON object per row. Batches are appended in the
+/// order they are written, so the output keeps the row order of the input.
+pub struct PredictionWriter {
+    path: String,
+    sink: PredictionSink,
+    columns: Option<Vec<String>>,
+    rows: usize,
+}
+
+impl PredictionWriter {
+    /// Create the output file, choosing the format from its extension
+    pub fn create(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let file = || File::create(path).map_err(|e| SymError::Io(format!("Failed to create {}: {}", path, e)));
+
+        let sink = match extension.as_deref() {
+            Some("csv") => PredictionSink::Csv(Box::new(csv::Writer::from_writer(file()?))),
+            Some("jsonl") | Some("ndjson") => PredictionSink::JsonLines(BufWriter::new(file()?)),
+            _ => {
+                return Err(SymError::Usage(format!(
+                    "Unsupported output file {}: expected a .csv or .jsonl extension",
+                    path
+                )).into())
+            }
+        };
+
+        Ok(PredictionWriter {
+            path: path.to_string(),
+            sink,
+            columns: None,
+            rows: 0,
+        })
+    }
+
+    /// Append the rows of `input` with the matching rows of `predictions`
+    ///
+    /// `predictions` must hold one row per input row: `Reply::predictions` checks
+    /// the server reply, so a mismatch here is a bug of the caller.
+    pub fn write_batch(&mut self, input: &DataFrame, predictions: &Predictions) -> Result<(), SymError> {
+        if predictions.data.len() != input.data.len() {
+            return Err(SymError::Other(format!(
+                "Got {} prediction rows for {} input rows",
+                predictions.data.len(),
+                input.data.len()
+            )));
+        }
+        let path = &self.path;
+        let failed = |e: &dyn std::fmt::Display| SymError::Io(format!("Failed to write {}: {}", path, e));
+
+        // The columns are fixed by the first batch; the CSV header is written with it
+        if self.columns.is_none() {
+            let columns = output_columns(&input.attribute_names, &predictions.attribute_names);
+            if let PredictionSink::Csv(writer) = &mut self.sink {
+                writer.write_record(&columns).map_err(|e| failed(&e))?;
+            }
+            self.columns = Some(columns);
+        }
+        let columns = self.columns.as_ref().unwrap();
+        let num_inputs = input.attribute_names.len();
+
+        for (i, row) in input.data.iter().enumerate() {
+            match &mut self.sink {
+                PredictionSink::Csv(writer) => {
+                    let predicted = (0..columns.len() - num_inputs).map(|c| predictions.cell(i, c));
+                    let record: Vec<String> = row.iter().cloned().chain(predicted).collect();
+                    writer.write_record(&record).map_err(|e| failed(&e))?;
+                }
+                PredictionSink::JsonLines(writer) => {
+                    // Written field by field to keep the column order
+                    let values = row
+                        .iter()
+                        .map(|v| Value::String(v.clone()))
+                        .chain((num_inputs..columns.len()).map(|c| {
+                            predictions.data[i].get(c - num_inputs).cloned().unwrap_or(Value::Null)
+                        }));
+                    let fields: Vec<String> = columns
+                        .iter()
+                        .zip(values)
+                        .map(|(name, value)| format!("{}:{}", Value::String(name.clone()), value))
+                        .collect();
+                    writeln!(writer, "{{{}}}", fields.join(",")).map_err(|e| failed(&e))?;
+                }
+            }
+        }
+
+        self.rows += input.data.len();
+        Ok(())
+    }
+
+    /// Flush the file and return the number of rows written
+    pub fn finish(self) -> Result<usize, SymError> {
+        match self.sink {
+            PredictionSink::Csv(mut writer) => writer.flush(),
+            PredictionSink::JsonLines(mut writer) => writer.flush(),
+        }
+        .map_err(|e| SymError::Io(format!("Failed to write {}: {}", self.path, e)))?;
+        Ok(self.rows)
+    }
+}
+
+/// Input columns followed by the prediction columns
+/// A prediction column named like an input column gets a `_predicted` suffix
+fn output_columns(input: &[String], predicted: &[String]) -> Vec<String> {
+    let mut columns = input.to_vec();
+    for name in predicted {
+        let name = if columns.contains(name) {
+            format!("{}_predicted", name)
+        } else {
+            name.clone()
+        };
+        columns.push(name);
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str, options: &CsvOptions) -> (Vec<String>, Vec<Vec<String>>) {
+        let reader = CsvBatchReader::new(text.as_bytes(), 100, options).unwrap();
+        let names = reader.attribute_names().to_vec();
+        (names, reader.read_all().unwrap())
+    }
+
+    #[test]
+    fn quoted_fields_keep_delimiters_newlines_and_spaces() {
+        let (names, rows) = read("name,comment\n\"Smith, J\",\"line one\nline two\"\n\" padded \",x\n", &CsvOptions::default());
+        assert_eq!(names, vec!["name", "comment"]);
+        assert_eq!(rows, vec![vec!["Smith, J", "line one\nline two"], vec![" padded ", "x"]]);
+    }
+
+    #[test]
+    fn escaped_quotes_are_unescaped() {
+        let (_, rows) = read("a,b\n\"say \"\"hi\"\"\",1\n", &CsvOptions::default());
+        assert_eq!(rows, vec![vec!["say \"hi\"", "1"]]);
+    }
+
+    #[test]
+    fn crlf_line_endings_and_empty_lines() {
+        let (names, rows) = read("a,b\r\n1,2\r\n\r\n3,4\r\n", &CsvOptions::default());
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(rows, vec![vec!["1", "2"], vec!["3", "4"]]);
+    }
+
+    #[test]
+    fn bom_is_stripped_from_the_header() {
+        let (names, rows) = read("\u{feff}a,b\n1,2\n", &CsvOptions::default());
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(rows, vec![vec!["1", "2"]]);
+    }
+
+    #[test]
+    fn custom_delimiter() {
+        let options = CsvOptions::new("semicolon", false, None).unwrap();
+        let (names, rows) = read("a;b\n1,5;\"x;y\"\n", &options);
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(rows, vec![vec!["1,5", "x;y"]]);
+        assert_eq!(CsvOptions::new("tab", false, None).unwrap().delimiter, b'\t');
+        assert!(CsvOptions::new("::", false, None).is_err());
+    }
+
+    #[test]
+    fn no_header_uses_the_given_columns() {
+        let options = CsvOptions::new(",", true, Some("a, b")).unwrap();
+        let (names, rows) = read("1,2\n3,4\n", &options);
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(rows, vec![vec!["1", "2"], vec!["3", "4"]]);
+        assert!(CsvOptions::new(",", true, None).is_err());
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        let mut reader = CsvBatchReader::new("a,b\n1,2\n3\n".as_bytes(), 100, &CsvOptions::default()).unwrap();
+        let err = reader.next_batch().unwrap_err();
+        assert_eq!(err.to_string(), "Line 3 has 1 fields, expected 2");
+    }
+
+    #[test]
+    fn batches_are_split_by_row_count() {
+        let mut reader = CsvBatchReader::new("a\n1\n2\n3\n".as_bytes(), 2, &CsvOptions::default()).unwrap();
+        assert_eq!(reader.next_batch().unwrap().unwrap().len(), 2);
+        assert_eq!(reader.next_batch().unwrap().unwrap().len(), 1);
+        assert!(reader.next_batch().unwrap().is_none());
+    }
+}
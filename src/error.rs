@@ -0,0 +1,136 @@
+use crate::client::SymResponse;
+use crate::config::ConfigError;
+use crate::responses::ServerResponse;
+use reqwest::StatusCode;
+
+/// Error reported by the client and the command line tool
+///
+/// Every variant maps to a process exit code, see `exit_code`.
+#[derive(Debug, thiserror::Error)]
+pub enum SymError {
+    /// Invalid command line arguments
+    #[error("{0}")]
+    Usage(String),
+
+    /// Configuration file or secret key could not be loaded
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// A file could not be read or written
+    #[error("{0}")]
+    Io(String),
+
+    /// A data file could not be parsed, or does not match the expected columns
+    #[error("{0}")]
+    Csv(String),
+
+    /// A request could not be signed, or the server rejected the credentials
+    #[error("{0}")]
+    Auth(String),
+
+    /// The server answered with an error status
+    #[error("Request failed with status: {status}{}", .message.as_ref().map(|m| format!(": {}", m)).unwrap_or_default())]
+    Http {
+        status: StatusCode,
+        /// `statusString` of the server error body, if any
+        message: Option<String>,
+        /// Raw response body
+        body: String,
+    },
+
+    /// The server could not be reached or did not answer in time
+    #[error("{0}")]
+    Network(String),
+
+    /// WebSocket failure, or a message rejected by the server
+    #[error("{0}")]
+    WebSocket(String),
+
+    /// An asynchronous job failed or did not finish in time
+    #[error("{message}")]
+    Job { job_id: String, message: String },
+
+    /// Any other failure
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SymError {
+    /// Build the error for a response with a non-2xx status
+    /// 401 and 403 are reported as `Auth`, other statuses as `Http`
+    pub fn from_response(response: &SymResponse) -> Self {
+        let message = ServerResponse::from_body(&response.body)
+            .ok()
+            .and_then(|r| r.status_string)
+            .filter(|m| !m.is_empty());
+
+        match response.status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => SymError::Auth(format!(
+                "Server rejected the credentials ({}){}",
+                response.status,
+                message.map(|m| format!(": {}", m)).unwrap_or_default()
+            )),
+            status => SymError::Http {
+                status,
+                message,
+                body: response.body.clone(),
+            },
+        }
+    }
+
+    /// Convert any error to a `SymError`, based on its type
+    pub fn classify(error: Box<dyn std::error::Error>) -> Self {
+        let error = match error.downcast::<SymError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let error = match error.downcast::<ConfigError>() {
+            Ok(e) => return SymError::Config(*e),
+            Err(e) => e,
+        };
+
+        let message = error.to_string();
+        if error.is::<std::io::Error>() {
+            SymError::Io(message)
+        } else if error.is::<csv::Error>() {
+            SymError::Csv(message)
+        } else if error.is::<reqwest::Error>() {
+            SymError::Network(message)
+        } else if error.is::<tokio_tungstenite::tungstenite::Error>() {
+            SymError::WebSocket(message)
+        } else if error.is::<base64::DecodeError>() {
+            SymError::Auth(format!("Invalid secret key: {}", message))
+        } else {
+            SymError::Other(message)
+        }
+    }
+
+    /// Process exit code for this error
+    ///
+    /// | Code | Error |
+    /// |------|-------|
+    /// | 1    | Other |
+    /// | 2    | Usage |
+    /// | 3    | Config |
+    /// | 4    | Io |
+    /// | 5    | Csv |
+    /// | 6    | Auth |
+    /// | 7    | Http |
+    /// | 8    | Network |
+    /// | 9    | WebSocket |
+    /// | 10   | Job |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SymError::Other(_) => 1,
+            SymError::Usage(_) => 2,
+            SymError::Config(_) => 3,
+            SymError::Io(_) => 4,
+            SymError::Csv(_) => 5,
+            SymError::Auth(_) => 6,
+            SymError::Http { .. } => 7,
+            SymError::Network(_) => 8,
+            SymError::WebSocket(_) => 9,
+            SymError::Job { .. } => 10,
+        }
+    }
+}
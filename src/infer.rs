@@ -0,0 +1,191 @@
+use crate::data::{CsvBatchReader, CsvOptions};
+use crate::error::SymError;
+
+/// Value of `--types` that asks for the types to be inferred from the data
+pub const AUTO_TYPES: &str = "auto";
+
+/// Number of rows scanned when inferring types from a file
+pub const DEFAULT_SAMPLE_ROWS: usize = 1000;
+
+/// Continuous attribute
+pub const CONTINUOUS: &str = "C";
+/// Binary (0/1) attribute
+pub const BINARY: &str = "B";
+/// Categorical attribute
+pub const CATEGORICAL: &str = "S";
+
+#[derive(Debug, Clone)]
+struct ColumnStats {
+    non_empty: usize,
+    numeric: bool,
+    binary: bool,
+}
+
+impl Default for ColumnStats {
+    fn default() -> Self {
+        ColumnStats {
+            non_empty: 0,
+            numeric: true,
+            binary: true,
+        }
+    }
+}
+
+/// Proposes an attribute type per column from observed values
+///
+/// A column is binary (`B`) if every value is 0 or 1, continuous (`C`) if every
+/// value is numeric, and categorical (`S`) otherwise. Empty values are ignored,
+/// and a column without any value is continuous.
+#[derive(Debug, Clone)]
+pub struct TypeInferrer {
+    columns: Vec<ColumnStats>,
+}
+
+impl TypeInferrer {
+    /// Create an inferrer for `num_columns` columns
+    pub fn new(num_columns: usize) -> Self {
+        TypeInferrer {
+            columns: vec![ColumnStats::default(); num_columns],
+        }
+    }
+
+    /// Observe one row of values
+    pub fn observe(&mut self, row: &[String]) {
+        for (stats, value) in self.columns.iter_mut().zip(row) {
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            stats.non_empty += 1;
+            match value.parse::<f64>() {
+                Ok(v) if v.is_finite() => {
+                    if v != 0.0 && v != 1.0 {
+                        stats.binary = false;
+                    }
+                }
+                _ => {
+                    stats.numeric = false;
+                    stats.binary = false;
+                }
+            }
+        }
+    }
+
+    /// Get the proposed type of every column
+    pub fn types(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|stats| {
+                let t = if stats.non_empty == 0 {
+                    CONTINUOUS
+                } else if stats.binary {
+                    BINARY
+                } else if stats.numeric {
+                    CONTINUOUS
+                } else {
+                    CATEGORICAL
+                };
+                t.to_string()
+            })
+            .collect()
+    }
+}
+
+/// Infer attribute types from rows already in memory
+pub fn infer_types(num_columns: usize, rows: &[Vec<String>]) -> Vec<String> {
+    let mut inferrer = TypeInferrer::new(num_columns);
+    for row in rows {
+        inferrer.observe(row);
+    }
+    inferrer.types()
+}
+
+/// Infer attribute types from the first `sample_rows` rows of a file
+pub fn infer_file_types(
+    path: &str,
+    options: &CsvOptions,
+    sample_rows: usize,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut reader = CsvBatchReader::open(path, sample_rows, options)?;
+    let num_columns = reader.attribute_names().len();
+    let sample = reader.next_batch()?.unwrap_or_default();
+    Ok(infer_types(num_columns, &sample))
+}
+
+/// Check if a `--types` value asks for inference
+pub fn is_auto(types: Option<&str>) -> bool {
+    match types {
+        None => true,
+        Some(t) => t.trim().eq_ignore_ascii_case(AUTO_TYPES),
+    }
+}
+
+/// Parse an explicit comma-separated list of types and check it matches the number of columns
+pub fn parse_types(
+    types: &str,
+    num_columns: usize,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let attribute_types: Vec<String> = types
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .collect();
+
+    // Validate that the number of types matches the number of columns
+    if attribute_types.len() != num_columns {
+        return Err(SymError::Usage(format!(
+            "Number of attribute types ({}) does not match number of columns ({})",
+            attribute_types.len(),
+            num_columns
+        )).into());
+    }
+
+    Ok(attribute_types)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[&[&str]]) -> Vec<Vec<String>> {
+        values.iter().map(|r| r.iter().map(|v| v.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn binary_continuous_and_categorical_columns() {
+        let data = rows(&[&["0", "5.1", "setosa"], &["1", "4", "virginica"], &["1.0", "-2e3", "setosa"]]);
+        assert_eq!(infer_types(3, &data), vec!["B", "C", "S"]);
+    }
+
+    #[test]
+    fn empty_values_are_ignored() {
+        let data = rows(&[&["", "", "1"], &[" ", "", ""], &["", "", "0"]]);
+        assert_eq!(infer_types(3, &data), vec!["C", "C", "B"]);
+        assert_eq!(infer_types(2, &[]), vec!["C", "C"]);
+    }
+
+    #[test]
+    fn mixed_numeric_and_text_values_are_categorical() {
+        let data = rows(&[&["1", "2.5"], &["0", "n/a"], &["yes", "3"]]);
+        assert_eq!(infer_types(2, &data), vec!["S", "S"]);
+        // Non-finite numbers are not continuous values
+        assert_eq!(infer_types(1, &rows(&[&["1.5"], &["NaN"]])), vec!["S"]);
+    }
+
+    #[test]
+    fn only_the_sample_rows_of_a_file_are_read() {
+        // The first DEFAULT_SAMPLE_ROWS rows are binary, the next one is text
+        let mut text = String::from("flag\n");
+        for i in 0..DEFAULT_SAMPLE_ROWS {
+            text.push_str(if i % 2 == 0 { "0\n" } else { "1\n" });
+        }
+        text.push_str("unknown\n");
+        let path = std::env::temp_dir().join(format!("sym-infer-sample-{}.csv", std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        let path = path.to_str().unwrap();
+
+        let options = CsvOptions::default();
+        assert_eq!(infer_file_types(path, &options, DEFAULT_SAMPLE_ROWS).unwrap(), vec!["B"]);
+        assert_eq!(infer_file_types(path, &options, DEFAULT_SAMPLE_ROWS + 1).unwrap(), vec!["S"]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+//! Client library for the SymetryML REST server
+//!
+//! `SymClient` exposes typed async methods for the REST endpoints used by the
+//! `sym-rest-rc` command line tool.
+
+pub mod auth;
+pub mod client;
+pub mod common;
+pub mod config;
+pub mod connection;
+pub mod data;
+pub mod error;
+pub mod infer;
+pub mod responses;
+pub mod transport;
+pub mod ws;
+
+pub use client::{PollOptions, SymClient, SymResponse};
+pub use error::SymError;
+pub use responses::{JobState, JobStatus, ModelInfo, Predictions, ProjectInfo, ServerResponse};
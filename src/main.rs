@@ -1,18 +1,31 @@
+#[macro_use]
+mod output;
 mod cli;
-mod config;
 mod commands;
-mod auth;
-mod common;
 
 use clap::Parser;
+use serde_json::json;
+use std::time::Duration;
+use sym_rest_rc::client::DryRun;
+use sym_rest_rc::{common, config, SymError};
 use cli::{Cli, Commands, ProjectCommands, ModelCommands, EvtwrapperCommands};
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    output::init(cli.output);
+    if cli.dry_run {
+        config::Config::enable_dry_run(DryRun { show_secret: cli.show_secret });
+    }
 
     // Auto-load configuration before executing any command
-    let config_result = config::Config::auto_load(cli.config);
+    let overrides = cli.overrides();
+    let config_result = config::Config::auto_load(cli.config, cli.profile, &overrides);
+    if config_result.is_ok() {
+        for warning in config::Config::warnings() {
+            eprintln!("Warning: {}", warning);
+        }
+    }
 
     let result = match cli.command {
         Commands::Config => {
@@ -22,110 +35,189 @@ async fn main() {
                 Err(e) => Err(e.into())
             }
         }
-        _ => {
+        Commands::Doctor => commands::doctor::handle_doctor(config_result).await,
+        command => {
             // For all other commands, config must be loaded
             match config_result {
-                Ok(_) => {
-                    // Config loaded successfully, execute the command
-                    match cli.command {
-                        Commands::Config => unreachable!(),
-                        Commands::Project(project_cmd) => {
-                            match project_cmd.command {
-                                ProjectCommands::Create(args) => {
-                                    commands::projects::create::handle_create(args.name, args.r#type, args.params, args.hist).await
-                                }
-                                ProjectCommands::Delete(args) => {
-                                    commands::projects::delete::handle_delete(args.project).await
-                                }
-                                ProjectCommands::Info(args) => {
-                                    commands::projects::info::handle_info(args.project).await
-                                }
-                            }
-                        }
-                        Commands::Model(model_cmd) => {
-                            match model_cmd.command {
-                                ModelCommands::Build(args) => {
-                                    commands::models::build::handle_build(
-                                        args.project,
-                                        args.name,
-                                        args.r#type,
-                                        args.targets,
-                                        args.inputs,
-                                        args.target_names,
-                                        args.input_names,
-                                        args.params
-                                    ).await
-                                }
-                                ModelCommands::Delete(args) => {
-                                    commands::models::delete::handle_delete(args.project, args.model).await
-                                }
-                                ModelCommands::Autoselect(args) => {
-                                    commands::models::autoselect::handle_autoselect(
-                                        args.project,
-                                        args.model,
-                                        args.task,
-                                        args.val_file,
-                                        args.val_df,
-                                        args.targets,
-                                        args.inputs,
-                                        args.target_names,
-                                        args.input_names,
-                                        args.params
-                                    ).await
-                                }
-                                ModelCommands::Info(args) => {
-                                    commands::models::info::handle_info(args.project, args.model).await
-                                }
-                                ModelCommands::Predict(args) => {
-                                    if args.use_ws {
-                                        commands::models::predict_ws::handle_predict(args.project, args.model, args.df, args.file).await
-                                    } else {
-                                        commands::models::predict_rest::handle_predict(args.project, args.model, args.df, args.file).await
-                                    }
-                                }
-                            }
-                        }
-                        Commands::Evtwrapper(evtwrapper_cmd) => {
-                            match evtwrapper_cmd.command {
-                                EvtwrapperCommands::Create(args) => {
-                                    commands::evtwrapper::create::handle_create(args.project, args.model, args.params).await
-                                }
-                                EvtwrapperCommands::Delete(args) => {
-                                    commands::evtwrapper::delete::handle_delete(args.project, args.model).await
-                                }
-                            }
-                        }
-                        Commands::Learn(args) => {
-                            if args.use_ws {
-                                commands::learn_ws::handle_learn(args.project, args.file, args.types).await
-                            } else {
-                                commands::learn_rest::handle_learn(args.project, args.file, args.types).await
-                            }
-                        }
-                        Commands::Job(args) => {
-                            commands::job_rest::handle_job(args.id).await
-                        }
-                    }
-                }
+                Ok(_) => match command {
+                    Commands::Run(args) => commands::run::handle_run(args.file).await,
+                    command => run_command(command).await,
+                },
                 Err(e) => Err(e.into())
             }
         }
     };
 
     if let Err(e) = result {
+        let e = SymError::classify(e);
         eprintln!("Error: {}", e);
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 }
 
-fn handle_config(loaded_from: &str) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Configuration loaded from: {}", loaded_from);
-    println!("Host: {}", config::Config::host());
-    println!("Port: {}", config::Config::port());
-    println!("User: {}", config::Config::user());
-    println!("Secret Key: {}", config::Config::secretkey());
-    println!("Use WebSocket for Learn: {}", config::Config::use_ws_for_learn());
-    println!("Use WebSocket for Predict: {}", config::Config::use_ws_for_predit());
-    Ok(())
+/// Execute a command that needs the loaded configuration
+/// Also used for the steps of a pipeline (see `commands::run`)
+async fn run_command(command: Commands) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Commands::Config | Commands::Doctor | Commands::Run(_) => {
+            Err(SymError::Usage("This command cannot be run from a pipeline".to_string()).into())
+        }
+        Commands::Project(project_cmd) => {
+            match project_cmd.command {
+                ProjectCommands::Create(args) => {
+                    commands::projects::create::handle_create(args.name, args.r#type, args.params, args.hist).await
+                }
+                ProjectCommands::Delete(args) => {
+                    commands::projects::delete::handle_delete(args.project).await
+                }
+                ProjectCommands::Info(args) => {
+                    commands::projects::info::handle_info(args.project).await
+                }
+            }
+        }
+        Commands::Model(model_cmd) => {
+            match model_cmd.command {
+                ModelCommands::Build(args) => {
+                    commands::models::build::handle_build(
+                        args.project,
+                        args.name,
+                        args.r#type,
+                        args.targets,
+                        args.inputs,
+                        args.target_names,
+                        args.input_names,
+                        args.params,
+                        args.wait.then(|| Duration::from_secs(args.wait_timeout))
+                    ).await
+                }
+                ModelCommands::Delete(args) => {
+                    commands::models::delete::handle_delete(args.project, args.model).await
+                }
+                ModelCommands::Autoselect(args) => match args.csv.options() {
+                    Ok(csv) => {
+                        commands::models::autoselect::handle_autoselect(
+                            args.project,
+                            args.model,
+                            args.task,
+                            args.val_file,
+                            args.val_df,
+                            csv,
+                            args.types,
+                            args.targets,
+                            args.inputs,
+                            args.target_names,
+                            args.input_names,
+                            args.params,
+                            args.wait.then(|| Duration::from_secs(args.wait_timeout))
+                        ).await
+                    }
+                    Err(e) => Err(e),
+                },
+                ModelCommands::Info(args) => {
+                    commands::models::info::handle_info(args.project, args.model).await
+                }
+                ModelCommands::Predict(args) => match args.csv.options() {
+                    Ok(csv) => {
+                        commands::models::predict::handle_predict(
+                            args.project,
+                            args.model,
+                            args.df,
+                            args.file,
+                            csv,
+                            args.types,
+                            args.out,
+                            args.transport.kind(config::Config::use_ws_for_predit()),
+                            args.batch_rows.map(|n| n as usize),
+                            args.ws_window as usize
+                        ).await
+                    }
+                    Err(e) => Err(e),
+                },
+            }
+        }
+        Commands::Evtwrapper(evtwrapper_cmd) => {
+            match evtwrapper_cmd.command {
+                EvtwrapperCommands::Create(args) => {
+                    commands::evtwrapper::create::handle_create(args.project, args.model, args.params).await
+                }
+                EvtwrapperCommands::Delete(args) => {
+                    commands::evtwrapper::delete::handle_delete(args.project, args.model).await
+                }
+            }
+        }
+        Commands::Learn(args) => match args.csv.options() {
+            Ok(csv) => {
+                commands::learn::handle_learn(
+                    args.project,
+                    args.file,
+                    csv,
+                    args.types,
+                    args.transport.kind(config::Config::use_ws_for_learn()),
+                    args.batch_rows.map(|n| n as usize),
+                    args.ws_window as usize,
+                    args.wait.then(|| Duration::from_secs(args.wait_timeout))
+                ).await
+            }
+            Err(e) => Err(e),
+        },
+        Commands::Raw(args) => {
+            commands::raw::handle_raw(args.method, args.resource, args.query, args.body).await
+        }
+        Commands::Job(args) => {
+            commands::job_rest::handle_job(
+                args.id,
+                args.watch.then(|| Duration::from_secs(args.wait_timeout))
+            ).await
+        }
+    }
 }
 
+fn handle_config(loaded_from: &str) -> Result<(), Box<dyn std::error::Error>> {
+    msg!("Configuration loaded from: {}", loaded_from);
+    msg!("Profile: {}", config::Config::profile().unwrap_or("(default)"));
+    msg!("Host: {}", config::Config::host());
+    msg!("Port: {}", config::Config::port());
+    msg!("User: {}", config::Config::user());
+    msg!(
+        "Secret Key: {} (from {})",
+        config::redact(&config::Config::secretkey()),
+        config::Config::secretkey_source()
+    );
+    msg!("Use WebSocket for Learn: {}", config::Config::use_ws_for_learn());
+    msg!("Use WebSocket for Predict: {}", config::Config::use_ws_for_predit());
+    if !config::Config::env_vars().is_empty() {
+        msg!("Environment overrides: {}", config::Config::env_vars().join(", "));
+    }
+
+    let profiles = config::Config::profiles();
+    if !profiles.is_empty() {
+        msg!();
+        msg!("Available profiles:");
+        for (name, p) in profiles {
+            let marker = if Some(name.as_str()) == config::Config::profile() { "*" } else { " " };
+            // Values not set in the profile are inherited from the top level of the file
+            msg!(
+                "  {} {:<16} host: {}, port: {}, user: {}",
+                marker,
+                name,
+                p.host.as_deref().unwrap_or("(inherited)"),
+                p.port.map(|v| v.to_string()).unwrap_or_else(|| "(inherited)".to_string()),
+                p.user.as_deref().unwrap_or("(inherited)")
+            );
+        }
+    }
+
+    output::emit(&json!({
+        "loadedFrom": loaded_from,
+        "profile": config::Config::profile(),
+        "host": config::Config::host(),
+        "port": config::Config::port(),
+        "user": config::Config::user(),
+        "secretKey": config::redact(&config::Config::secretkey()),
+        "secretKeySource": config::Config::secretkey_source(),
+        "useWsForLearn": config::Config::use_ws_for_learn(),
+        "useWsForPredict": config::Config::use_ws_for_predit(),
+        "envOverrides": config::Config::env_vars(),
+        "profiles": profiles.keys().collect::<Vec<_>>(),
+    }))
+}
@@ -0,0 +1,217 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+/// Result of the last command while capturing: None when not capturing
+static CAPTURE: Mutex<Option<Option<Value>>> = Mutex::new(None);
+
+/// Format of the command results written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable messages (default)
+    Text,
+    /// JSON result on stdout, messages on stderr
+    Json,
+    /// YAML result on stdout, messages on stderr
+    Yaml,
+    /// Plain text table on stdout, messages on stderr
+    Table,
+}
+
+/// Print a progress or status message
+/// Goes to stdout in text mode and to stderr otherwise, so stdout only holds the result
+macro_rules! msg {
+    () => {
+        $crate::output::message(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::message(format_args!($($arg)*))
+    };
+}
+
+/// Set the output format for the rest of the program
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// Get the selected output format
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or(OutputFormat::Text)
+}
+
+/// Check if human readable output is selected
+pub fn is_text() -> bool {
+    format() == OutputFormat::Text
+}
+
+/// Print a message, see `msg!`
+pub fn message(args: fmt::Arguments) {
+    if is_text() {
+        println!("{}", args);
+    } else {
+        eprintln!("{}", args);
+    }
+}
+
+/// Keep the results passed to `emit` instead of printing them, until `take_capture`
+pub fn start_capture() {
+    *CAPTURE.lock().unwrap() = Some(None);
+}
+
+/// Stop capturing and return the last result emitted since `start_capture`
+pub fn take_capture() -> Option<Value> {
+    CAPTURE.lock().unwrap().take().flatten()
+}
+
+/// Store a result if capturing
+/// Returns false if not capturing
+fn capture<T: Serialize>(result: &T) -> Result<bool, Box<dyn std::error::Error>> {
+    match CAPTURE.lock().unwrap().as_mut() {
+        Some(slot) => {
+            *slot = Some(serde_json::to_value(result)?);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Print the structured result of a command
+/// Nothing is printed in text mode, where handlers already printed a readable form
+pub fn emit<T: Serialize>(result: &T) -> Result<(), Box<dyn std::error::Error>> {
+    if capture(result)? {
+        return Ok(());
+    }
+    match format() {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(result)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(result)?),
+        OutputFormat::Table => print!("{}", render_table(&serde_json::to_value(result)?)),
+    }
+    Ok(())
+}
+
+/// Print one element of a streamed result as soon as it is available
+///
+/// Each element is a JSON line in text and json formats, a YAML document in yaml
+/// format and a table followed by a blank line in table format. Nothing is printed
+/// while capturing, where only the final result of a command is kept.
+pub fn emit_line<T: Serialize>(item: &T) -> Result<(), Box<dyn std::error::Error>> {
+    if CAPTURE.lock().unwrap().is_some() {
+        return Ok(());
+    }
+    match format() {
+        OutputFormat::Text | OutputFormat::Json => println!("{}", serde_json::to_string(item)?),
+        OutputFormat::Yaml => print!("---\n{}", serde_yaml::to_string(item)?),
+        OutputFormat::Table => println!("{}", render_table(&serde_json::to_value(item)?)),
+    }
+    Ok(())
+}
+
+/// Record the final result of a command that streamed its output with `emit_line`
+/// The result is kept while capturing, and not printed otherwise
+pub fn emit_streamed<T: Serialize>(result: &T) -> Result<(), Box<dyn std::error::Error>> {
+    capture(result).map(|_| ())
+}
+
+/// Print the structured result of a command
+/// In text mode `text` is printed instead
+pub fn emit_with_text<T: Serialize>(result: &T, text: &dyn fmt::Display) -> Result<(), Box<dyn std::error::Error>> {
+    if is_text() {
+        print!("{}", text);
+        capture(result).map(|_| ())
+    } else {
+        emit(result)
+    }
+}
+
+/// Render a value as a plain text table
+///
+/// An array of objects becomes one row per element with a column per key.
+/// An object becomes KEY/VALUE rows, with an array of objects rendered as a
+/// nested table below it. Nested values are shown as compact JSON.
+fn render_table(value: &Value) -> String {
+    match value {
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+            let mut columns: Vec<String> = Vec::new();
+            for item in items {
+                for key in item.as_object().into_iter().flat_map(|o| o.keys()) {
+                    if !columns.contains(key) {
+                        columns.push(key.clone());
+                    }
+                }
+            }
+            let rows: Vec<Vec<String>> = items
+                .iter()
+                .map(|item| columns.iter().map(|c| cell(item.get(c))).collect())
+                .collect();
+            format_table(&columns, &rows)
+        }
+        Value::Object(map) => {
+            let mut out = String::new();
+            let mut nested = Vec::new();
+            let mut rows = Vec::new();
+            for (key, v) in map {
+                match v {
+                    Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_object) => {
+                        nested.push((key, v));
+                    }
+                    _ => rows.push(vec![key.clone(), cell(Some(v))]),
+                }
+            }
+            if !rows.is_empty() {
+                out.push_str(&format_table(&["KEY".to_string(), "VALUE".to_string()], &rows));
+            }
+            for (key, v) in nested {
+                out.push_str(&format!("\n{}:\n", key));
+                out.push_str(&render_table(v));
+            }
+            out
+        }
+        other => format!("{}\n", cell(Some(other))),
+    }
+}
+
+/// Render a single table cell
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Align columns, with a header line and a separator
+fn format_table(columns: &[String], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.len()).collect();
+    for row in rows {
+        for (i, c) in row.iter().enumerate() {
+            widths[i] = widths[i].max(c.chars().count());
+        }
+    }
+
+    let line = |cells: &[String]| -> String {
+        cells
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{:<width$}", c, width = widths[i]))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    out.push_str(&line(&header));
+    out.push('\n');
+    out.push_str(&widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("  "));
+    out.push('\n');
+    for row in rows {
+        out.push_str(&line(row));
+        out.push('\n');
+    }
+    out
+}
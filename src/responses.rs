@@ -0,0 +1,409 @@
+use crate::client::SymResponse;
+use crate::common::MLContext;
+use crate::error::SymError;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Envelope returned by the SymetryML REST server
+///
+/// `values` holds the payload. Depending on the endpoint, a value is either a
+/// JSON object or a JSON document encoded as a string.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerResponse {
+    #[serde(default)]
+    pub status_code: String,
+    #[serde(default)]
+    pub status_string: Option<String>,
+    #[serde(default)]
+    pub values: Map<String, Value>,
+}
+
+impl ServerResponse {
+    /// Parse a response body
+    pub fn from_body(body: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(body)
+    }
+
+    /// Check if the server reported an error in the envelope
+    pub fn is_error(&self) -> bool {
+        let code = self.status_code.to_ascii_uppercase();
+        code.contains("ERROR") || code.contains("FAIL")
+    }
+
+    /// Get a value by key, decoding it if it is a JSON document encoded as a string
+    pub fn value(&self, key: &str) -> Option<Value> {
+        self.values.get(key).map(decode_nested)
+    }
+}
+
+/// Decode a value holding a JSON document encoded as a string
+fn decode_nested(value: &Value) -> Value {
+    if let Value::String(s) = value {
+        if let Ok(parsed) = serde_json::from_str::<Value>(s) {
+            if parsed.is_object() || parsed.is_array() {
+                return parsed;
+            }
+        }
+    }
+    value.clone()
+}
+
+/// Parse the typed payload stored under `values.<key>` in a response envelope
+fn parse_payload<T: DeserializeOwned>(body: &str, key: &str) -> Result<T, serde_json::Error> {
+    let envelope = ServerResponse::from_body(body)?;
+    let payload = envelope
+        .value(key)
+        .ok_or_else(|| <serde_json::Error as serde::de::Error>::custom(format!("missing values.{} in the response", key)))?;
+    serde_json::from_value(payload)
+}
+
+/// Project information returned by `GET /projects/{pid}/info` in `values.info`
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectInfo {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename = "type")]
+    pub project_type: Option<String>,
+    #[serde(default)]
+    pub attribute_names: Vec<String>,
+    #[serde(default)]
+    pub attribute_types: Vec<String>,
+    #[serde(default)]
+    pub num_rows: Option<u64>,
+    /// IDs of the models built in the project
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Fields not covered above
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl ProjectInfo {
+    /// Parse project information from a response body
+    pub fn from_body(body: &str) -> Result<Self, serde_json::Error> {
+        parse_payload(body, "info")
+    }
+}
+
+impl fmt::Display for ProjectInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            writeln!(f, "Project: {}", name)?;
+        }
+        if let Some(t) = &self.project_type {
+            writeln!(f, "Type: {}", t)?;
+        }
+        if let Some(n) = self.num_rows {
+            writeln!(f, "Rows: {}", n)?;
+        }
+        writeln!(f, "Attributes ({}):", self.attribute_names.len())?;
+        for (i, name) in self.attribute_names.iter().enumerate() {
+            let attr_type = self.attribute_types.get(i).map(String::as_str).unwrap_or("?");
+            writeln!(f, "  {:>3}  {:<30} {}", i, name, attr_type)?;
+        }
+        if self.models.is_empty() {
+            writeln!(f, "Models: (none)")?;
+        } else {
+            writeln!(f, "Models: {}", self.models.join(", "))?;
+        }
+        write_other(f, &self.other)
+    }
+}
+
+/// Model information returned by `GET /projects/{pid}/models/{mid}` in `values.modelInfo`
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelInfo {
+    #[serde(default)]
+    pub model_id: Option<String>,
+    #[serde(default)]
+    pub algo: Option<String>,
+    #[serde(default)]
+    pub ml_context: Option<MLContext>,
+    /// Fields not covered above
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl ModelInfo {
+    /// Parse model information from a response body
+    pub fn from_body(body: &str) -> Result<Self, serde_json::Error> {
+        parse_payload(body, "modelInfo")
+    }
+}
+
+impl fmt::Display for ModelInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.model_id {
+            writeln!(f, "Model: {}", id)?;
+        }
+        if let Some(algo) = &self.algo {
+            writeln!(f, "Algorithm: {}", algo)?;
+        }
+        if let Some(ctx) = &self.ml_context {
+            if let Some(t) = &ctx.targets {
+                writeln!(f, "Targets (IDs): {}", t.join(","))?;
+            }
+            if let Some(t) = &ctx.target_attribute_names {
+                writeln!(f, "Target Names: {}", t.join(","))?;
+            }
+            if let Some(i) = &ctx.input_attributes {
+                writeln!(f, "Inputs (IDs): {}", i.join(","))?;
+            }
+            if let Some(i) = &ctx.input_attribute_names {
+                writeln!(f, "Input Names: {}", i.join(","))?;
+            }
+            if !ctx.extra_parameters.is_empty() {
+                writeln!(f, "Parameters:")?;
+                let mut params: Vec<_> = ctx.extra_parameters.iter().collect();
+                params.sort();
+                for (k, v) in params {
+                    writeln!(f, "  {} = {}", k, v)?;
+                }
+            }
+        }
+        write_other(f, &self.other)
+    }
+}
+
+/// Prediction results returned by `POST /projects/{pid}/predict/{mid}` in `values.dataframe`
+///
+/// Holds one row of predicted values per input row, in input order.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Predictions {
+    #[serde(default)]
+    pub attribute_names: Vec<String>,
+    #[serde(default)]
+    pub data: Vec<Vec<Value>>,
+}
+
+impl Predictions {
+    /// Parse prediction results from a response body
+    pub fn from_body(body: &str) -> Result<Self, serde_json::Error> {
+        parse_payload(body, "dataframe")
+    }
+
+    /// Get a predicted value as it should appear in a data file
+    pub fn cell(&self, row: usize, column: usize) -> String {
+        match self.data.get(row).and_then(|r| r.get(column)) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        }
+    }
+}
+
+/// State of an asynchronous job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    Done,
+    Failed,
+}
+
+impl FromStr for JobState {
+    type Err = String;
+
+    /// Map the `statusCode` of a job status response:
+    /// `ACCEPTED` while the job runs, `OK` once it is done, `ERROR` if it failed.
+    /// Any other value is an error.
+    fn from_str(status_code: &str) -> Result<Self, Self::Err> {
+        match status_code {
+            "ACCEPTED" => Ok(JobState::Running),
+            "OK" => Ok(JobState::Done),
+            "ERROR" => Ok(JobState::Failed),
+            other => Err(format!("Unknown job state '{}'", other)),
+        }
+    }
+}
+
+impl JobState {
+    /// Check if the job will not change state anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Failed)
+    }
+}
+
+impl Serialize for JobState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobState::Running => write!(f, "RUNNING"),
+            JobState::Done => write!(f, "DONE"),
+            JobState::Failed => write!(f, "FAILED"),
+        }
+    }
+}
+
+/// Job status returned by `GET /jobs/{id}`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub job_id: String,
+    pub state: JobState,
+    pub message: Option<String>,
+    /// Job result or progress details
+    pub values: Map<String, Value>,
+}
+
+impl JobStatus {
+    /// Build the job status from a response
+    ///
+    /// The state is the `statusCode` of the envelope, see `JobState::from_str`. A
+    /// response without a known state code is an error: the one sent by the
+    /// server for an error status, `Http` otherwise.
+    pub fn from_response(job_id: &str, response: &SymResponse) -> Result<Self, SymError> {
+        let state = ServerResponse::from_body(&response.body)
+            .map_err(|_| format!("Invalid status response for job {}", job_id))
+            .and_then(|envelope| match envelope.status_code.parse::<JobState>() {
+                Ok(state) => Ok((state, envelope)),
+                Err(e) => Err(format!("{} for job {}", e, job_id)),
+            });
+
+        match state {
+            Ok((state, envelope)) => Ok(JobStatus {
+                job_id: job_id.to_string(),
+                state,
+                message: envelope.status_string,
+                values: envelope.values,
+            }),
+            Err(_) if !response.is_success() => Err(SymError::from_response(response)),
+            Err(message) => Err(SymError::Http {
+                status: response.status,
+                message: Some(message),
+                body: response.body.clone(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Job: {}", self.job_id)?;
+        writeln!(f, "State: {}", self.state)?;
+        if let Some(m) = &self.message {
+            writeln!(f, "Message: {}", m)?;
+        }
+        write_other(f, &self.values)
+    }
+}
+
+/// Render a scalar JSON value without quotes
+fn display_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Render remaining fields as `key: value` lines, sorted by key
+fn write_other(f: &mut fmt::Formatter<'_>, other: &Map<String, Value>) -> fmt::Result {
+    let mut keys: Vec<&String> = other.keys().collect();
+    keys.sort();
+    for k in keys {
+        writeln!(f, "{}: {}", k, display_value(&other[k]))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn response(status: u16, body: &str) -> SymResponse {
+        SymResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            job_id: None,
+            date: None,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn project_info_from_a_string_encoded_payload() {
+        let body = r#"{"statusCode":"OK","statusString":"Project info","values":{"info":"{\"name\":\"iris\",\"type\":\"cpu\",\"attributeNames\":[\"sepal_length\",\"species\"],\"attributeTypes\":[\"C\",\"S\"],\"numRows\":150,\"models\":[\"m1\"],\"histogram\":true}"}}"#;
+        let info = ProjectInfo::from_body(body).unwrap();
+        assert_eq!(info.name.as_deref(), Some("iris"));
+        assert_eq!(info.project_type.as_deref(), Some("cpu"));
+        assert_eq!(info.attribute_names, vec!["sepal_length", "species"]);
+        assert_eq!(info.attribute_types, vec!["C", "S"]);
+        assert_eq!(info.num_rows, Some(150));
+        assert_eq!(info.models, vec!["m1"]);
+        assert_eq!(info.other["histogram"], Value::Bool(true));
+    }
+
+    #[test]
+    fn project_info_requires_values_info() {
+        let body = r#"{"statusCode":"OK","statusString":"","values":{"projectInfo":{"name":"iris"}}}"#;
+        let err = ProjectInfo::from_body(body).unwrap_err();
+        assert!(err.to_string().contains("missing values.info"), "{}", err);
+
+        // A bare payload is not an envelope
+        assert!(ProjectInfo::from_body(r#"{"name":"iris"}"#).is_err());
+    }
+
+    #[test]
+    fn model_info_from_an_object_payload() {
+        let body = r#"{"statusCode":"OK","values":{"modelInfo":{"modelId":"m1","algo":"lsvm","mlContext":{"targets":["4"],"inputAttributes":["0","1"],"extraParameters":{}},"trained":true}}}"#;
+        let info = ModelInfo::from_body(body).unwrap();
+        assert_eq!(info.model_id.as_deref(), Some("m1"));
+        assert_eq!(info.algo.as_deref(), Some("lsvm"));
+        assert_eq!(info.ml_context.unwrap().targets, Some(vec!["4".to_string()]));
+        assert_eq!(info.other["trained"], Value::Bool(true));
+
+        assert!(ModelInfo::from_body(r#"{"statusCode":"OK","values":{}}"#).is_err());
+    }
+
+    #[test]
+    fn predictions_from_a_string_encoded_dataframe() {
+        let body = r#"{"statusCode":"OK","statusString":"","values":{"dataframe":"{\"attributeNames\":[\"species_pred\"],\"data\":[[\"setosa\"],[1.5]]}"}}"#;
+        let predictions = Predictions::from_body(body).unwrap();
+        assert_eq!(predictions.attribute_names, vec!["species_pred"]);
+        assert_eq!(predictions.cell(0, 0), "setosa");
+        assert_eq!(predictions.cell(1, 0), "1.5");
+    }
+
+    #[test]
+    fn job_states_from_status_codes() {
+        let status = |status, code: &str| {
+            let body = format!(r#"{{"statusCode":"{}","statusString":"","values":{{}}}}"#, code);
+            JobStatus::from_response("7", &response(status, &body))
+        };
+        assert_eq!(status(202, "ACCEPTED").unwrap().state, JobState::Running);
+        assert_eq!(status(200, "OK").unwrap().state, JobState::Done);
+        assert_eq!(status(500, "ERROR").unwrap().state, JobState::Failed);
+
+        for code in ["RUNNING", "DONE", "FINISHED", "ok", ""] {
+            match status(200, code) {
+                Err(SymError::Http { message: Some(m), .. }) => {
+                    assert_eq!(m, format!("Unknown job state '{}' for job 7", code))
+                }
+                other => panic!("{}: {:?}", code, other),
+            }
+        }
+    }
+
+    #[test]
+    fn job_status_without_an_envelope() {
+        match JobStatus::from_response("7", &response(200, "not json")) {
+            Err(SymError::Http { message: Some(m), .. }) => assert_eq!(m, "Invalid status response for job 7"),
+            other => panic!("{:?}", other),
+        }
+        match JobStatus::from_response("7", &response(404, "Not Found")) {
+            Err(SymError::Http { status, .. }) => assert_eq!(status, StatusCode::NOT_FOUND),
+            other => panic!("{:?}", other),
+        }
+    }
+}
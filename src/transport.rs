@@ -0,0 +1,422 @@
+use crate::client::{PreparedRequest, SymClient, SymResponse};
+use crate::common::DataFrame;
+use crate::config;
+use crate::error::SymError;
+use crate::responses::{Predictions, ServerResponse};
+use crate::ws::{prepare_message, PreparedMessage, WsAck, WsSession};
+use reqwest::StatusCode;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+
+/// Future returned by `Transport` methods
+/// Boxed, since async functions in traits need a newer Rust than the 1.70 this crate supports
+pub type TransportFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Box<dyn std::error::Error>>> + 'a>>;
+
+/// How DataFrames are sent for learn and predict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    /// One signed REST request per DataFrame
+    Rest,
+    /// Signed messages on one WebSocket connection
+    Ws,
+}
+
+impl TransportKind {
+    /// Pick WebSocket when `use_ws` is set, REST otherwise
+    pub fn from_use_ws(use_ws: bool) -> Self {
+        if use_ws { TransportKind::Ws } else { TransportKind::Rest }
+    }
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransportKind::Rest => write!(f, "rest"),
+            TransportKind::Ws => write!(f, "ws"),
+        }
+    }
+}
+
+impl FromStr for TransportKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rest" => Ok(TransportKind::Rest),
+            "ws" | "websocket" => Ok(TransportKind::Ws),
+            _ => Err(format!("Invalid transport '{}': expected rest or ws", s)),
+        }
+    }
+}
+
+/// Reply of the server to one DataFrame
+#[derive(Debug, Clone)]
+pub struct Reply {
+    /// Sequence number of the DataFrame, starting at 1
+    pub seq: u64,
+    /// Number of rows in the DataFrame
+    pub rows: usize,
+    /// HTTP status of the response; None over WebSocket
+    pub status: Option<StatusCode>,
+    /// Value of the `sym-job-id` header for asynchronous REST operations
+    pub job_id: Option<String>,
+    pub body: String,
+}
+
+impl Reply {
+    /// Check if the server rejected the DataFrame
+    pub fn is_error(&self) -> bool {
+        match self.status {
+            Some(status) => !status.is_success(),
+            None => ServerResponse::from_body(&self.body)
+                .map(|r| r.is_error())
+                .unwrap_or(false),
+        }
+    }
+
+    /// Parse the body as JSON, falling back to a string if it isn't JSON
+    pub fn body_value(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body)
+            .unwrap_or_else(|_| serde_json::Value::String(self.body.clone()))
+    }
+
+    /// Error reported for a rejected DataFrame
+    pub fn error(&self) -> SymError {
+        match self.status {
+            Some(status) => SymError::from_response(&SymResponse {
+                status,
+                job_id: self.job_id.clone(),
+                date: None,
+                body: self.body.clone(),
+            }),
+            None => {
+                let message = ServerResponse::from_body(&self.body)
+                    .ok()
+                    .and_then(|r| r.status_string)
+                    .filter(|m| !m.is_empty());
+                SymError::WebSocket(format!(
+                    "Batch {} rejected by server{}",
+                    self.seq,
+                    message.map(|m| format!(": {}", m)).unwrap_or_default()
+                ))
+            }
+        }
+    }
+
+    /// Parse the predictions of an accepted DataFrame, checking that there is one row per input row
+    /// A reply that does not hold them is a response error, like a rejected DataFrame
+    pub fn predictions(&self) -> Result<Predictions, SymError> {
+        let predictions = Predictions::from_body(&self.body)
+            .map_err(|e| self.invalid(format!("Failed to read predictions for batch {}: {}", self.seq, e)))?;
+        if predictions.data.len() != self.rows {
+            return Err(self.invalid(format!(
+                "Server returned {} prediction rows for the {} rows of batch {}",
+                predictions.data.len(),
+                self.rows,
+                self.seq
+            )));
+        }
+        Ok(predictions)
+    }
+
+    /// Error for a reply whose body is not what the request expects
+    fn invalid(&self, message: String) -> SymError {
+        match self.status {
+            Some(status) => SymError::Http {
+                status,
+                message: Some(message),
+                body: self.body.clone(),
+            },
+            None => SymError::WebSocket(message),
+        }
+    }
+}
+
+impl From<WsAck> for Reply {
+    fn from(ack: WsAck) -> Self {
+        Reply {
+            seq: ack.seq,
+            rows: ack.rows,
+            status: None,
+            job_id: None,
+            body: ack.reply,
+        }
+    }
+}
+
+/// Sends DataFrames to a learn or predict endpoint
+///
+/// Replies are returned in the order the DataFrames were sent. A transport may
+/// return a reply later than the `send` call of its DataFrame; `finish` returns
+/// the replies still outstanding.
+pub trait Transport {
+    /// Kind of the transport
+    fn kind(&self) -> TransportKind;
+
+    /// Send a DataFrame and return the replies received so far
+    fn send<'a>(&'a mut self, dataframe: &'a DataFrame) -> TransportFuture<'a, Vec<Reply>>;
+
+    /// Wait for the replies to every DataFrame sent and close the transport
+    fn finish(self: Box<Self>) -> TransportFuture<'static, Vec<Reply>>;
+}
+
+/// Endpoint a transport sends DataFrames to
+#[derive(Debug, Clone)]
+enum Endpoint {
+    Learn { project: String },
+    Predict { project: String, model: String },
+}
+
+/// Transport sending one REST request per DataFrame and waiting for its response
+pub struct RestTransport {
+    client: SymClient,
+    endpoint: Endpoint,
+    next_seq: u64,
+}
+
+impl RestTransport {
+    /// Push DataFrames to a project
+    pub fn learn(client: SymClient, project: &str) -> Self {
+        Self::new(client, Endpoint::Learn { project: project.to_string() })
+    }
+
+    /// Score DataFrames with a model
+    pub fn predict(client: SymClient, project: &str, model: &str) -> Self {
+        Self::new(
+            client,
+            Endpoint::Predict {
+                project: project.to_string(),
+                model: model.to_string(),
+            },
+        )
+    }
+
+    fn new(client: SymClient, endpoint: Endpoint) -> Self {
+        RestTransport {
+            client,
+            endpoint,
+            next_seq: 1,
+        }
+    }
+}
+
+impl Transport for RestTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Rest
+    }
+
+    fn send<'a>(&'a mut self, dataframe: &'a DataFrame) -> TransportFuture<'a, Vec<Reply>> {
+        Box::pin(async move {
+            let response = match &self.endpoint {
+                Endpoint::Learn { project } => self.client.learn(project, dataframe).await?,
+                Endpoint::Predict { project, model } => self.client.predict(project, model, dataframe).await?,
+            };
+            let seq = self.next_seq;
+            self.next_seq += 1;
+            Ok(vec![Reply {
+                seq,
+                rows: dataframe.data.len(),
+                status: Some(response.status),
+                job_id: response.job_id,
+                body: response.body,
+            }])
+        })
+    }
+
+    fn finish(self: Box<Self>) -> TransportFuture<'static, Vec<Reply>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+}
+
+/// Transport sending signed messages on one WebSocket session
+/// Up to the window of the session are in flight, see `WsSession`
+pub struct WsTransport {
+    session: WsSession,
+}
+
+impl WsTransport {
+    /// Send the DataFrames on an open session
+    pub fn new(session: WsSession) -> Self {
+        WsTransport { session }
+    }
+}
+
+impl Transport for WsTransport {
+    fn kind(&self) -> TransportKind {
+        TransportKind::Ws
+    }
+
+    fn send<'a>(&'a mut self, dataframe: &'a DataFrame) -> TransportFuture<'a, Vec<Reply>> {
+        Box::pin(async move {
+            let (_, acks) = self.session.send(dataframe).await?;
+            Ok(acks.into_iter().map(Reply::from).collect())
+        })
+    }
+
+    fn finish(self: Box<Self>) -> TransportFuture<'static, Vec<Reply>> {
+        Box::pin(async move {
+            let acks = self.session.finish().await?;
+            Ok(acks.into_iter().map(Reply::from).collect())
+        })
+    }
+}
+
+/// Open a transport to push DataFrames to a project, using the loaded configuration
+/// `window` is the number of messages in flight over WebSocket
+pub async fn open_learn(
+    kind: TransportKind,
+    project: &str,
+    window: usize,
+) -> Result<Box<dyn Transport>, Box<dyn std::error::Error>> {
+    match kind {
+        TransportKind::Rest => Ok(Box::new(RestTransport::learn(SymClient::from_config()?, project))),
+        // extraKeys are [project]
+        TransportKind::Ws => open_ws("learn", vec![project.to_string()], window).await,
+    }
+}
+
+/// Open a transport to score DataFrames with a model, using the loaded configuration
+/// `window` is the number of messages in flight over WebSocket
+pub async fn open_predict(
+    kind: TransportKind,
+    project: &str,
+    model: &str,
+    window: usize,
+) -> Result<Box<dyn Transport>, Box<dyn std::error::Error>> {
+    match kind {
+        TransportKind::Rest => Ok(Box::new(RestTransport::predict(SymClient::from_config()?, project, model))),
+        // extraKeys are [project, model] (project first, then model)
+        TransportKind::Ws => open_ws("predict", vec![project.to_string(), model.to_string()], window).await,
+    }
+}
+
+/// Open a WebSocket session on `/symetry/ws/{endpoint}`
+async fn open_ws(
+    endpoint: &str,
+    extra_keys: Vec<String>,
+    window: usize,
+) -> Result<Box<dyn Transport>, Box<dyn std::error::Error>> {
+    let session = WsSession::connect(
+        &ws_url(endpoint),
+        config::Config::tls(),
+        config::Config::timeouts(),
+        config::Config::user(),
+        &config::Config::secretkey(),
+        extra_keys,
+        window,
+    )
+    .await?;
+    Ok(Box::new(WsTransport::new(session)))
+}
+
+/// URL of the WebSocket endpoint `/symetry/ws/{endpoint}` of the configured server
+fn ws_url(endpoint: &str) -> String {
+    format!(
+        "{}://{}:{}/symetry/ws/{}",
+        config::Config::tls().ws_scheme(),
+        config::Config::host(),
+        config::Config::port(),
+        endpoint
+    )
+}
+
+/// A DataFrame signed as a transport would send it, but not sent
+/// Printed by dry runs
+#[derive(Debug, Clone)]
+pub enum Prepared {
+    /// REST request
+    Rest(PreparedRequest),
+    /// WebSocket message, and the URL of the session it is sent on
+    Ws { url: String, message: PreparedMessage },
+}
+
+impl Prepared {
+    /// Format for display: a REST request as a curl command, a WebSocket message as sent
+    /// The signature is redacted unless `show_secret` is set
+    pub fn display(&self, show_secret: bool) -> String {
+        match self {
+            Prepared::Rest(request) => request.to_curl(show_secret),
+            Prepared::Ws { url, message } => {
+                format!("# WebSocket message to {}\n{}", url, message.display_frame(show_secret))
+            }
+        }
+    }
+
+    /// Structured form, with the signature redacted unless `show_secret` is set
+    pub fn to_value(&self, show_secret: bool) -> serde_json::Value {
+        match self {
+            Prepared::Rest(request) => {
+                let headers: serde_json::Map<String, serde_json::Value> = request
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match *name {
+                            "Authorization" if !show_secret => config::redact(value),
+                            _ => value.clone(),
+                        };
+                        (name.to_string(), value.into())
+                    })
+                    .collect();
+                serde_json::json!({
+                    "transport": TransportKind::Rest.to_string(),
+                    "method": request.method.as_str(),
+                    "url": request.url,
+                    "headers": headers,
+                    "body": request.body,
+                    "curl": request.to_curl(show_secret),
+                })
+            }
+            Prepared::Ws { url, message } => serde_json::json!({
+                "transport": TransportKind::Ws.to_string(),
+                "url": url,
+                "message": message.display_frame(show_secret),
+            }),
+        }
+    }
+}
+
+/// Sign a DataFrame as `open_learn` would send it, without connecting
+pub fn prepare_learn(
+    kind: TransportKind,
+    project: &str,
+    dataframe: &DataFrame,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+    match kind {
+        TransportKind::Rest => Ok(Prepared::Rest(SymClient::from_config()?.learn(project, dataframe).prepare()?)),
+        TransportKind::Ws => prepare_ws("learn", &[project.to_string()], dataframe),
+    }
+}
+
+/// Sign a DataFrame as `open_predict` would send it, without connecting
+pub fn prepare_predict(
+    kind: TransportKind,
+    project: &str,
+    model: &str,
+    dataframe: &DataFrame,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+    match kind {
+        TransportKind::Rest => Ok(Prepared::Rest(
+            SymClient::from_config()?.predict(project, model, dataframe).prepare()?,
+        )),
+        TransportKind::Ws => prepare_ws("predict", &[project.to_string(), model.to_string()], dataframe),
+    }
+}
+
+/// Sign a WebSocket message for `/symetry/ws/{endpoint}`
+fn prepare_ws(
+    endpoint: &str,
+    extra_keys: &[String],
+    dataframe: &DataFrame,
+) -> Result<Prepared, Box<dyn std::error::Error>> {
+    let url = ws_url(endpoint);
+    let message = prepare_message(
+        &url,
+        config::Config::user(),
+        &config::Config::secretkey(),
+        extra_keys,
+        dataframe,
+    )?;
+    Ok(Prepared::Ws { url, message })
+}
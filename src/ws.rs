@@ -0,0 +1,268 @@
+use crate::auth::AuthHeaders;
+use crate::common::DataFrame;
+use crate::connection::{connect_ws, describe_timeout, Timeouts, TlsSettings, WsStream};
+use crate::error::SymError;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::VecDeque;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// Default number of messages sent before waiting for an acknowledgement
+pub const DEFAULT_WINDOW: usize = 4;
+
+#[derive(Serialize)]
+struct WsHeaders {
+    headers: Vec<String>,
+    #[serde(rename = "extraKeys")]
+    extra_keys: Vec<String>,
+}
+
+/// A signed WebSocket message, before it is sent
+#[derive(Debug, Clone)]
+pub struct PreparedMessage {
+    /// Headers JSON: `{"headers":[date, md5, authorization, customer-id],"extraKeys":[...]}`
+    pub headers_json: String,
+    pub dataframe_json: String,
+    /// Signature included in the headers
+    pub authorization: String,
+}
+
+impl PreparedMessage {
+    /// The message as sent: `<header length>,<headers json><dataframe json>`
+    pub fn frame(&self) -> String {
+        // Add length prefix (LENGTH OF HEADER JSON, not total message!)
+        format!("{},{}{}", self.headers_json.len(), self.headers_json, self.dataframe_json)
+    }
+
+    /// The message for display, with the signature redacted unless `show_secret` is set
+    /// The length prefix is the one sent, counted on the headers before redaction.
+    pub fn display_frame(&self, show_secret: bool) -> String {
+        if show_secret {
+            return self.frame();
+        }
+        let headers = self
+            .headers_json
+            .replace(&self.authorization, &crate::config::redact(&self.authorization));
+        format!("{},{}{}", self.headers_json.len(), headers, self.dataframe_json)
+    }
+}
+
+/// Sign a WebSocket message for a DataFrame
+///
+/// The message is signed with `AuthHeaders::generate_ws`; see `build_message`
+/// for the arguments.
+pub fn prepare_message(
+    url: &str,
+    customer_id: &str,
+    secret_key: &str,
+    extra_keys: &[String],
+    dataframe: &DataFrame,
+) -> Result<PreparedMessage, Box<dyn std::error::Error>> {
+    // Convert DataFrame to JSON string (compact format)
+    let dataframe_json = serde_json::to_string(dataframe)?;
+
+    let auth = AuthHeaders::generate_ws(&dataframe_json, url, extra_keys, customer_id, secret_key)?;
+
+    // Headers: [timestamp, md5, authorization, customer-id]
+    let ws_headers = WsHeaders {
+        headers: vec![auth.sym_date, auth.content_md5, auth.authorization.clone(), customer_id.to_string()],
+        extra_keys: extra_keys.to_vec(),
+    };
+
+    Ok(PreparedMessage {
+        headers_json: serde_json::to_string(&ws_headers)?,
+        dataframe_json,
+        authorization: auth.authorization,
+    })
+}
+
+/// Build a signed WebSocket message for a DataFrame
+///
+/// Format: `<header length>,<headers json><dataframe json>`, signed with
+/// `AuthHeaders::generate_ws`.
+///
+/// # Arguments
+/// * `url` - WebSocket URL, including the scheme (e.g., "ws://host:8080/symetry/ws/learn")
+/// * `customer_id` - Customer ID
+/// * `secret_key` - Base64-encoded secret key
+/// * `extra_keys` - The project name for learn, the project and model names for predict
+/// * `dataframe` - Data to send
+pub fn build_message(
+    url: &str,
+    customer_id: &str,
+    secret_key: &str,
+    extra_keys: &[String],
+    dataframe: &DataFrame,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(prepare_message(url, customer_id, secret_key, extra_keys, dataframe)?.frame())
+}
+
+/// Check if a text message is the server's status reply to a DataFrame message
+pub fn is_status_reply(text: &str) -> bool {
+    text.contains("\"statusCode\"") || text.contains("DONE") || text.contains("FINISHED")
+}
+
+/// Acknowledgement of one message sent on a `WsSession`
+#[derive(Debug, Clone)]
+pub struct WsAck {
+    /// Sequence number of the message, starting at 1
+    pub seq: u64,
+    /// Number of rows in the message
+    pub rows: usize,
+    /// Status reply sent by the server
+    pub reply: String,
+}
+
+impl WsAck {
+    /// Check if the server rejected the message
+    pub fn is_error(&self) -> bool {
+        crate::responses::ServerResponse::from_body(&self.reply)
+            .map(|r| r.is_error())
+            .unwrap_or(false)
+    }
+}
+
+/// A message waiting for its acknowledgement
+struct Pending {
+    seq: u64,
+    rows: usize,
+}
+
+/// Long-lived WebSocket session sending many signed DataFrame messages
+///
+/// Up to `window` messages are sent before waiting for an acknowledgement. The
+/// server replies to messages in the order it receives them, so each status reply
+/// acknowledges the oldest message still in flight.
+pub struct WsSession {
+    write: SplitSink<WsStream, Message>,
+    read: SplitStream<WsStream>,
+    url: String,
+    customer_id: String,
+    secret_key: String,
+    extra_keys: Vec<String>,
+    window: usize,
+    idle_timeout: Option<std::time::Duration>,
+    next_seq: u64,
+    in_flight: VecDeque<Pending>,
+}
+
+impl WsSession {
+    /// Open a session
+    ///
+    /// # Arguments
+    /// * `url` - WebSocket URL, also used in the signature
+    /// * `tls` - TLS settings for wss:// URLs
+    /// * `timeouts` - Connect timeout, and idle timeout while waiting for acknowledgements
+    /// * `customer_id` - Customer ID
+    /// * `secret_key` - Base64-encoded secret key
+    /// * `extra_keys` - Extra keys signed with every message (see `build_message`)
+    /// * `window` - Maximum number of unacknowledged messages
+    pub async fn connect(
+        url: &str,
+        tls: &TlsSettings,
+        timeouts: &Timeouts,
+        customer_id: &str,
+        secret_key: &str,
+        extra_keys: Vec<String>,
+        window: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (write, read) = connect_ws(url, tls, timeouts).await?.split();
+        Ok(WsSession {
+            write,
+            read,
+            url: url.to_string(),
+            customer_id: customer_id.to_string(),
+            secret_key: secret_key.to_string(),
+            extra_keys,
+            window: window.max(1),
+            idle_timeout: timeouts.ws_idle,
+            next_seq: 1,
+            in_flight: VecDeque::new(),
+        })
+    }
+
+    /// Number of messages sent but not acknowledged yet
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Sign and send a DataFrame, first waiting for acknowledgements while the window is full
+    /// Returns the sequence number of the message and the acknowledgements received while waiting
+    pub async fn send(
+        &mut self,
+        dataframe: &DataFrame,
+    ) -> Result<(u64, Vec<WsAck>), Box<dyn std::error::Error>> {
+        let mut acks = Vec::new();
+        while self.in_flight.len() >= self.window {
+            acks.push(self.recv_ack().await?);
+        }
+
+        let message = build_message(
+            &self.url,
+            &self.customer_id,
+            &self.secret_key,
+            &self.extra_keys,
+            dataframe,
+        )?;
+        self.write.send(Message::Text(message)).await?;
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.in_flight.push_back(Pending {
+            seq,
+            rows: dataframe.data.len(),
+        });
+        Ok((seq, acks))
+    }
+
+    /// Wait for the acknowledgement of the oldest message in flight
+    pub async fn recv_ack(&mut self) -> Result<WsAck, Box<dyn std::error::Error>> {
+        if self.in_flight.is_empty() {
+            return Err(SymError::WebSocket("No message waiting for an acknowledgement".to_string()).into());
+        }
+
+        loop {
+            let next = match self.idle_timeout {
+                Some(t) => tokio::time::timeout(t, self.read.next()).await.map_err(|_| {
+                    SymError::Network(format!(
+                        "{} elapsed without a reply from the server ({} message(s) not acknowledged)",
+                        describe_timeout("ws_idle_timeout", self.idle_timeout),
+                        self.in_flight.len()
+                    ))
+                })?,
+                None => self.read.next().await,
+            };
+            let Some(message) = next else {
+                break;
+            };
+            match message? {
+                Message::Text(text) if is_status_reply(&text) => {
+                    let pending = self.in_flight.pop_front().unwrap();
+                    return Ok(WsAck {
+                        seq: pending.seq,
+                        rows: pending.rows,
+                        reply: text,
+                    });
+                }
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+
+        Err(SymError::WebSocket(format!(
+            "WebSocket closed with {} message(s) not acknowledged",
+            self.in_flight.len()
+        )).into())
+    }
+
+    /// Wait for every message in flight to be acknowledged, then close the connection
+    pub async fn finish(mut self) -> Result<Vec<WsAck>, Box<dyn std::error::Error>> {
+        let mut acks = Vec::new();
+        while !self.in_flight.is_empty() {
+            acks.push(self.recv_ack().await?);
+        }
+        let _ = self.write.close().await;
+        Ok(acks)
+    }
+}
@@ -0,0 +1,1007 @@
+//! End-to-end tests of the command line tool against the mock server
+
+mod support;
+
+use serde_json::Value;
+use std::process::Output;
+use support::{temp_file, MockServer};
+
+const IRIS: &str = "sepal_length,sepal_width,species\n\
+5.1,3.5,setosa\n\
+4.9,3.0,setosa\n\
+6.3,3.3,virginica\n\
+5.8,2.7,virginica\n\
+6.4,3.2,versicolor\n";
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+fn assert_success(output: &Output) {
+    assert!(
+        output.status.success(),
+        "command failed\nstdout:\n{}\nstderr:\n{}",
+        stdout(output),
+        stderr(output)
+    );
+}
+
+fn json_output(output: &Output) -> Value {
+    assert_success(output);
+    serde_json::from_str(&stdout(output)).expect("stdout is not a JSON document")
+}
+
+#[test]
+fn project_create_info_delete() {
+    let server = MockServer::start();
+
+    assert_success(&server.run(&["project", "create", "--name", "p1", "--type", "cpu"]));
+    assert!(server.state().projects.contains_key("p1"));
+
+    let info = json_output(&server.run(&["--output", "json", "project", "info", "--project", "p1"]));
+    assert_eq!(info["name"], "p1");
+
+    assert_success(&server.run(&["project", "delete", "--project", "p1"]));
+    assert!(server.state().projects.is_empty());
+    assert!(server.state().requests.iter().all(|r| r.authenticated));
+}
+
+#[test]
+fn wrong_secret_key_is_rejected() {
+    let server = MockServer::start();
+    server.write_config("b3RoZXIta2V5", "");
+
+    let output = server.run(&["project", "create", "--name", "p1", "--type", "cpu"]);
+    assert!(!output.status.success());
+    assert!(server.state().projects.is_empty());
+    assert!(server.state().requests.iter().all(|r| !r.authenticated));
+}
+
+#[test]
+fn learn_rest_in_batches() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let file = temp_file("learn-rest.csv", IRIS);
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--batch-rows", "2",
+    ]));
+    assert_eq!(result["rows"], 5);
+    assert_eq!(result["batches"], 3);
+    assert_eq!(result["lastBatch"]["batch"], 3);
+    assert_eq!(result["lastBatch"]["rows"], 1);
+
+    let state = server.state();
+    let project = &state.projects["p1"];
+    assert_eq!(project.rows, 5);
+    assert_eq!(project.attribute_types, vec!["C", "C", "S"]);
+}
+
+#[test]
+fn learn_waits_for_the_jobs_after_sending_every_batch() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let file = temp_file("learn-wait.csv", IRIS);
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--batch-rows", "2", "--wait",
+    ]));
+    assert_eq!(result["batches"], 3);
+    assert_eq!(result["lastBatch"]["jobId"], "3");
+    assert_eq!(result["lastBatch"]["job"]["state"], "DONE");
+
+    // Every batch is sent before the first job is polled, and every job is polled
+    let state = server.state();
+    let paths: Vec<&str> = state.requests.iter().map(|r| r.path.as_str()).collect();
+    let last_learn = paths.iter().rposition(|p| p.ends_with("/learn")).unwrap();
+    let first_poll = paths.iter().position(|p| p.contains("/jobs/")).unwrap();
+    assert!(last_learn < first_poll, "{:?}", paths);
+    for id in ["1", "2", "3"] {
+        assert!(paths.iter().any(|p| p.ends_with(&format!("/jobs/{}", id))), "{:?}", paths);
+    }
+}
+
+#[test]
+fn learn_ws_pipelined() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let file = temp_file("learn-ws.csv", IRIS);
+
+    assert_success(&server.run(&[
+        "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--use-ws", "--batch-rows", "1", "--ws-window", "3",
+    ]));
+
+    let state = server.state();
+    assert_eq!(state.projects["p1"].rows, 5);
+    assert_eq!(state.ws_messages["learn"], 5);
+}
+
+#[test]
+fn configured_transport_and_override() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    server.write_config(support::SECRET_KEY, "use_ws_for_learn = true");
+    let file = temp_file("learn-transport.csv", IRIS);
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "learn", "--project", "p1", "--file", file.to_str().unwrap(),
+    ]));
+    assert_eq!(result["transport"], "ws");
+    assert_eq!(server.state().ws_messages["learn"], 1);
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--transport", "rest",
+    ]));
+    assert_eq!(result["transport"], "rest");
+    let state = server.state();
+    assert_eq!(state.ws_messages["learn"], 1);
+    assert_eq!(state.projects["p1"].rows, 10);
+}
+
+#[test]
+fn build_and_wait_for_job() {
+    let server = MockServer::start();
+    server.add_project("p1");
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "model", "build", "--project", "p1", "--name", "m1", "--type", "lsvm",
+        "--target-names", "species", "--input-names", "sepal_length", "--wait",
+    ]));
+    assert_eq!(result["jobId"], "1");
+    assert_eq!(result["job"]["state"], "DONE");
+    assert!(server.state().projects["p1"].models.contains_key("m1"));
+}
+
+#[test]
+fn predict_rest_to_csv() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    let file = temp_file("predict-rest.csv", IRIS);
+    let out = temp_file("predict-rest-out.csv", "");
+
+    assert_success(&server.run(&[
+        "model", "predict", "--project", "p1", "--model", "m1", "--file", file.to_str().unwrap(),
+        "--out", out.to_str().unwrap(),
+    ]));
+
+    let written = std::fs::read_to_string(&out).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines[0], "sepal_length,sepal_width,species,m1_prediction");
+    assert_eq!(lines[1], "5.1,3.5,setosa,pred-5.1");
+    assert_eq!(lines.len(), 6);
+}
+
+#[test]
+fn predict_ws_streaming_keeps_row_order() {
+    let server = MockServer::start();
+    let file = temp_file("predict-ws.csv", IRIS);
+    let out = temp_file("predict-ws-out.jsonl", "");
+
+    assert_success(&server.run(&[
+        "model", "predict", "--project", "p1", "--model", "m1", "--file", file.to_str().unwrap(), "--use-ws",
+        "--batch-rows", "2", "--ws-window", "2", "--out", out.to_str().unwrap(),
+    ]));
+
+    let written = std::fs::read_to_string(&out).unwrap();
+    let predictions: Vec<String> = written
+        .lines()
+        .map(|l| serde_json::from_str::<Value>(l).unwrap()["m1_prediction"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(predictions, vec!["pred-5.1", "pred-4.9", "pred-6.3", "pred-5.8", "pred-6.4"]);
+    assert_eq!(server.state().ws_messages["predict"], 3);
+}
+
+#[test]
+fn evtwrapper_create_and_delete() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+
+    assert_success(&server.run(&[
+        "evtwrapper", "create", "--project", "p1", "--model", "m1", "--params", "threshold=0.5,filter=a=b",
+    ]));
+    {
+        let state = server.state();
+        assert_eq!(state.projects["p1"].evt_wrappers, vec!["m1"]);
+        let request = state.requests.last().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/symetry/rest/c1/projects/p1/m1/evtwrapper"));
+        let body: Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body, serde_json::json!({ "threshold": "0.5", "filter": "a=b" }));
+    }
+
+    assert_success(&server.run(&["evtwrapper", "delete", "--project", "p1", "--model", "m1"]));
+    let state = server.state();
+    assert!(state.projects["p1"].evt_wrappers.is_empty());
+    let request = state.requests.last().unwrap();
+    assert_eq!((request.method.as_str(), request.path.as_str()), ("DELETE", "/symetry/rest/c1/projects/p1/m1/evtwrapper"));
+}
+
+#[test]
+fn evtwrapper_rejects_malformed_params() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+
+    for params in ["threshold", "=0.5", "threshold=0.5,"] {
+        let output = server.run(&["evtwrapper", "create", "--project", "p1", "--model", "m1", "--params", params]);
+        assert_eq!(output.status.code(), Some(2), "{}", params);
+        assert!(stderr(&output).contains("expected key=value"), "{}", stderr(&output));
+    }
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn predict_streams_batches_to_stdout() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    let file = temp_file("predict-stream.csv", IRIS);
+
+    let output = server.run(&[
+        "--output", "json", "model", "predict", "--project", "p1", "--model", "m1", "--file", file.to_str().unwrap(),
+        "--batch-rows", "2",
+    ]);
+    assert_success(&output);
+    let lines: Vec<Value> = stdout(&output).lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["batch"], 1);
+    assert_eq!(lines[0]["predictions"]["data"][1][0], "pred-4.9");
+    assert_eq!(lines[2]["rows"], 1);
+}
+
+#[test]
+fn predict_streams_batches_as_yaml_documents() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    let file = temp_file("predict-stream-yaml.csv", IRIS);
+
+    let output = server.run(&[
+        "--output", "yaml", "model", "predict", "--project", "p1", "--model", "m1", "--file", file.to_str().unwrap(),
+        "--batch-rows", "2",
+    ]);
+    assert_success(&output);
+    let documents: Vec<Value> = serde_yaml::Deserializer::from_str(&stdout(&output))
+        .map(|d| serde::Deserialize::deserialize(d).unwrap())
+        .collect();
+    assert_eq!(documents.len(), 3);
+    assert_eq!(documents[0]["batch"], 1);
+    assert_eq!(documents[0]["predictions"]["data"][1][0], "pred-4.9");
+    assert_eq!(documents[2]["rows"], 1);
+}
+
+#[test]
+fn predict_streams_batches_as_tables() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    let file = temp_file("predict-stream-table.csv", IRIS);
+
+    let output = server.run(&[
+        "--output", "table", "model", "predict", "--project", "p1", "--model", "m1", "--file", file.to_str().unwrap(),
+        "--batch-rows", "2",
+    ]);
+    assert_success(&output);
+    let text = stdout(&output);
+    // One table per batch, with a row per prediction and a column per predicted attribute
+    assert_eq!(text.matches("predictions:\nM1_PREDICTION\n").count(), 3, "{}", text);
+    assert!(text.contains("\npred-5.1\npred-4.9\n"), "{}", text);
+    assert!(!text.contains("attributeNames"), "{}", text);
+}
+
+#[test]
+fn job_watch_until_done() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    assert_success(&server.run(&[
+        "model", "autoselect", "--project", "p1", "--model", "m1", "--task", "classification",
+        "--val-df", r#"{"attributeNames":["a"],"data":[["1"]],"attributeTypes":["C"]}"#,
+        "--target-names", "a", "--input-names", "a",
+    ]));
+
+    let status = json_output(&server.run(&["--output", "json", "job", "--id", "1", "--watch"]));
+    assert_eq!(status["state"], "DONE");
+}
+
+#[test]
+fn transient_status_is_retried() {
+    let server = MockServer::start();
+    server.write_config(support::SECRET_KEY, "retry_backoff_ms = 1");
+    server.add_project("p1");
+    server.state().fail_next.extend([503, 502]);
+
+    assert_success(&server.run(&["project", "info", "--project", "p1"]));
+    assert_eq!(server.state().requests.len(), 3);
+}
+
+#[test]
+fn learn_is_retried_only_with_opt_in() {
+    let server = MockServer::start();
+    server.write_config(support::SECRET_KEY, "retry_backoff_ms = 1");
+    server.add_project("p1");
+    let file = temp_file("learn-retry.csv", IRIS);
+    let args = ["learn", "--project", "p1", "--file", file.to_str().unwrap()];
+
+    server.state().fail_next.push_back(503);
+    assert!(!server.run(&args).status.success());
+    assert_eq!(server.state().requests.len(), 1);
+    assert_eq!(server.state().projects["p1"].rows, 0);
+
+    server.state().fail_next.push_back(503);
+    let mut retried = vec!["--retry-non-idempotent"];
+    retried.extend(args);
+    assert_success(&server.run(&retried));
+    assert_eq!(server.state().projects["p1"].rows, 5);
+}
+
+#[test]
+fn request_timeout_is_named_in_the_error() {
+    let server = MockServer::start();
+    server.state().silent = true;
+
+    let output = server.run(&["--request-timeout", "1", "--retries", "0", "project", "info", "--project", "p1"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("request_timeout (1s)"), "{}", stderr);
+}
+
+#[test]
+fn ws_idle_timeout_is_named_in_the_error() {
+    let server = MockServer::start();
+    server.state().silent = true;
+    let file = temp_file("learn-idle.csv", IRIS);
+
+    let output = server.run(&[
+        "--ws-idle-timeout", "1", "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--use-ws",
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ws_idle_timeout (1s)"), "{}", stderr);
+}
+
+#[cfg(unix)]
+fn set_mode(path: &std::path::Path, mode: u32) {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn secret_key_from_file() {
+    let server = MockServer::start();
+    let key_file = temp_file("secret-600.key", &format!("{}\n", support::SECRET_KEY));
+    set_mode(&key_file, 0o600);
+    server.write_config("", &format!("secretkey_file = \"{}\"", key_file.display()));
+
+    let output = server.run(&["project", "create", "--name", "p1", "--type", "cpu"]);
+    assert_success(&output);
+    assert!(server.state().requests.iter().all(|r| r.authenticated));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Warning"));
+}
+
+#[cfg(unix)]
+#[test]
+fn readable_secret_key_file_warns() {
+    let server = MockServer::start();
+    let key_file = temp_file("secret-644.key", support::SECRET_KEY);
+    set_mode(&key_file, 0o644);
+    server.write_config("", &format!("secretkey_file = \"{}\"", key_file.display()));
+
+    let output = server.run(&["project", "create", "--name", "p1", "--type", "cpu"]);
+    assert_success(&output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("chmod 600"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn secret_key_from_command() {
+    let server = MockServer::start();
+    server.write_config("", &format!("secretkey_command = \"echo {}\"", support::SECRET_KEY));
+
+    assert_success(&server.run(&["project", "create", "--name", "p1", "--type", "cpu"]));
+    assert!(server.state().requests.iter().all(|r| r.authenticated));
+
+    server.write_config("", "secretkey_command = \"exit 3\"");
+    let output = server.run(&["project", "info", "--project", "p1"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn config_redacts_secret_key() {
+    let server = MockServer::start();
+
+    let text = server.run(&["config"]);
+    assert_success(&text);
+    assert!(!stdout(&text).contains(support::SECRET_KEY));
+
+    let json = json_output(&server.run(&["--output", "json", "config"]));
+    assert!(!json.to_string().contains(support::SECRET_KEY));
+    assert_eq!(json["secretKeySource"], "config file");
+}
+
+#[test]
+fn environment_and_flags_override_the_file() {
+    let server = MockServer::start();
+    let port = server.port().to_string();
+    server.write_config(support::SECRET_KEY, "");
+
+    let config = |env_port: &str, args: &[&str]| {
+        json_output(
+            &support::command()
+                .arg("--config")
+                .arg(server.config_path())
+                .args(["--output", "json"])
+                .args(args)
+                .arg("config")
+                .env("SML_PORT", env_port)
+                .env("SML_USER", "from-env")
+                .output()
+                .unwrap(),
+        )
+    };
+
+    // Environment variables override the file, flags override both
+    let result = config("4242", &[]);
+    assert_eq!(result["port"], 4242);
+    assert_eq!(result["user"], "from-env");
+    assert_eq!(result["envOverrides"], serde_json::json!(["SML_PORT", "SML_USER"]));
+    let result = config("4242", &["--port", &port, "--user", "from-flag"]);
+    assert_eq!(result["port"], server.port());
+    assert_eq!(result["user"], "from-flag");
+
+    let output = support::command()
+        .arg("--config")
+        .arg(server.config_path())
+        .arg("config")
+        .env("SML_PORT", "not-a-port")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("SML_PORT"), "{}", stderr(&output));
+}
+
+#[test]
+fn profiles_are_selected_by_flag_or_environment() {
+    let server = MockServer::start();
+    server.write_config(
+        support::SECRET_KEY,
+        "[profiles.staging]\nhost = \"staging.example.com\"\nuser = \"c2\"\n\n[profiles.local]\nuse_ws_for_learn = true\n",
+    );
+
+    let config = |profile_env: Option<&str>, args: &[&str]| {
+        let mut command = support::command();
+        command.arg("--config").arg(server.config_path()).args(["--output", "json"]).args(args).arg("config");
+        if let Some(profile) = profile_env {
+            command.env("SML_PROFILE", profile);
+        }
+        command.output().unwrap()
+    };
+
+    // Without a selection the top-level settings are used
+    let result = json_output(&config(None, &[]));
+    assert_eq!(result["profile"], Value::Null);
+    assert_eq!(result["host"], "127.0.0.1");
+    assert_eq!(result["profiles"], serde_json::json!(["local", "staging"]));
+
+    // Values the profile does not set are inherited from the top level
+    let result = json_output(&config(None, &["--profile", "staging"]));
+    assert_eq!(result["profile"], "staging");
+    assert_eq!(result["host"], "staging.example.com");
+    assert_eq!(result["user"], "c2");
+    assert_eq!(result["port"], server.port());
+    assert_eq!(result["secretKeySource"], "config file");
+
+    let result = json_output(&config(Some("local"), &[]));
+    assert_eq!(result["profile"], "local");
+    assert_eq!(result["host"], "127.0.0.1");
+    assert_eq!(result["user"], support::CUSTOMER_ID);
+    assert_eq!(result["useWsForLearn"], true);
+
+    // The flag takes precedence over SML_PROFILE
+    let result = json_output(&config(Some("local"), &["--profile", "staging"]));
+    assert_eq!(result["profile"], "staging");
+
+    // The inherited settings are used for requests
+    server.add_project("p1");
+    assert_success(&server.run(&["--profile", "local", "project", "info", "--project", "p1"]));
+
+    for output in [config(None, &["--profile", "missing"]), config(Some("missing"), &[])] {
+        assert_eq!(output.status.code(), Some(3));
+        assert!(
+            stderr(&output).contains("Profile 'missing' not found in config (available: local, staging)"),
+            "{}",
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn tls_trusts_every_certificate_of_the_ca_bundle() {
+    let server = MockServer::start_tls();
+    server.add_project("p1");
+    let file = temp_file("learn-tls.csv", IRIS);
+    let learn = ["learn", "--project", "p1", "--file", file.to_str().unwrap(), "--use-ws"];
+
+    // The server certificate is signed by the second certificate of the bundle
+    let bundle = support::ca_bundle_path();
+    server.write_config(support::SECRET_KEY, &format!("tls = true\nca_cert = {:?}", bundle.to_str().unwrap()));
+    assert_success(&server.run(&["project", "info", "--project", "p1"]));
+    assert_success(&server.run(&learn));
+    let state = server.state();
+    assert!(state.requests.iter().all(|r| r.authenticated));
+    assert_eq!(state.ws_messages["learn"], 1);
+    drop(state);
+
+    // Without the bundle the certificate is not trusted
+    server.write_config(support::SECRET_KEY, "tls = true");
+    assert_eq!(server.run(&["project", "info", "--project", "p1"]).status.code(), Some(8));
+    assert_eq!(server.run(&learn).status.code(), Some(9));
+}
+
+#[test]
+fn settings_from_environment_without_a_file() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    // No rc.conf in the working directory and no config in the home directory
+    let home = temp_file("no-config-home", "");
+    std::fs::remove_file(&home).unwrap();
+    std::fs::create_dir_all(&home).unwrap();
+
+    let run = |env: &[(&str, String)]| {
+        support::command()
+            .args(["project", "info", "--project", "p1"])
+            .current_dir(&home)
+            .env("HOME", &home)
+            .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+            .output()
+            .unwrap()
+    };
+
+    assert_success(&run(&[
+        ("SML_HOST", "127.0.0.1".to_string()),
+        ("SML_PORT", server.port().to_string()),
+        ("SML_USER", support::CUSTOMER_ID.to_string()),
+        ("SML_SK", support::SECRET_KEY.to_string()),
+    ]));
+
+    let output = run(&[("SML_HOST", "127.0.0.1".to_string())]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("SML_PORT"), "{}", stderr(&output));
+}
+
+#[test]
+fn raw_signed_request() {
+    let server = MockServer::start();
+    let body = temp_file("raw-body.json", r#"{"mlcontext":{}}"#);
+    let body_arg = format!("@{}", body.display());
+
+    let result = json_output(&server.run(&[
+        "--output", "json", "raw", "post", "projects", "--query", "pid=p9", "--query", "type=cpu", "--body", &body_arg,
+    ]));
+    assert_eq!(result["status"], 200);
+    assert_eq!(result["resource"], "/symetry/rest/c1/projects");
+    assert_eq!(result["query"], "pid=p9&type=cpu");
+    assert!(server.state().projects.contains_key("p9"));
+
+    let result = json_output(&server.run(&["--output", "json", "raw", "GET", "/symetry/rest/c1/projects"]));
+    assert_eq!(result["response"]["values"]["projects"][0], "p9");
+
+    let output = server.run(&["raw", "GET", "nothing/here"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(stdout(&output).contains("404"), "{}", stdout(&output));
+}
+
+#[test]
+fn dry_run_prints_a_rest_request_as_curl() {
+    let server = MockServer::start();
+
+    let output = server.run(&["--dry-run", "project", "create", "--name", "p1", "--type", "cpu"]);
+    assert_success(&output);
+    let text = stdout(&output);
+    assert!(text.contains(&format!("curl -X POST 'http://127.0.0.1:{}/symetry/rest/c1/projects?pid=p1", server.port())), "{}", text);
+    assert!(text.contains("-H 'Authorization: ********"), "{}", text);
+    assert!(text.contains("--data-raw '{"), "{}", text);
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn dry_run_shows_the_signature_the_server_checks() {
+    let server = MockServer::start();
+
+    let output = server.run(&["--dry-run", "--show-secret", "raw", "GET", "projects"]);
+    assert_success(&output);
+    let text = stdout(&output);
+    let header = |name: &str| {
+        text.lines()
+            .find_map(|l| l.trim().strip_prefix(&format!("-H '{}: ", name)))
+            .map(|v| v.trim_end_matches(" \\").trim_end_matches('\''))
+            .unwrap_or_default()
+            .to_string()
+    };
+    assert!(text.contains("-H 'Content-MD5;'"), "{}", text);
+    let auth = sym_rest_rc::auth::AuthHeaders {
+        content_md5: String::new(),
+        sym_date: header("Sym-date"),
+        authorization: header("Authorization"),
+    };
+    auth.verify("GET", "/symetry/rest/c1/projects", None, None, support::CUSTOMER_ID, support::SECRET_KEY)
+        .unwrap();
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn dry_run_prints_a_websocket_message_with_its_length() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let file = temp_file("dry-run.csv", IRIS);
+
+    let output = server.run(&["--dry-run", "--show-secret", "learn", "--project", "p1", "--file", file.to_str().unwrap(), "--transport", "ws"]);
+    assert_success(&output);
+    let frame = stdout(&output).lines().skip_while(|l| !l.starts_with("# WebSocket")).nth(1).unwrap().to_string();
+    let (length, rest) = frame.split_once(',').unwrap();
+    assert!(rest[..length.parse::<usize>().unwrap()].ends_with(r#""extraKeys":["p1"]}"#), "{}", frame);
+    assert!(!server.state().ws_messages.contains_key("learn"));
+}
+
+#[test]
+fn dry_run_with_json_output_prints_one_document() {
+    let server = MockServer::start();
+
+    let result = json_output(&server.run(&["--dry-run", "--output", "json", "model", "info", "--project", "p1", "--model", "m1"]));
+    assert_eq!(result["dryRun"], true);
+    assert_eq!(result["method"], "GET");
+    assert!(result["headers"]["Authorization"].as_str().unwrap().starts_with("********"), "{}", result);
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn dry_run_pipeline_prints_only_the_first_step() {
+    let server = MockServer::start();
+    let pipeline = temp_file(
+        "dry-run.toml",
+        r#"
+[[steps]]
+id = "create"
+command = "project create"
+name = "p2"
+type = "cpu"
+
+[[steps]]
+command = "project delete"
+project = "${create.project}"
+"#,
+    );
+
+    let result = json_output(&server.run(&["--dry-run", "--output", "json", "run", pipeline.to_str().unwrap()]));
+    assert_eq!(result["passed"], true);
+    assert_eq!(result["dryRun"], true);
+    assert_eq!(result["steps"][0]["status"], "ok");
+    assert_eq!(result["steps"][1]["status"], "skipped");
+    assert_eq!(result["results"]["create"]["method"], "POST");
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn dry_run_doctor_runs_only_the_local_checks() {
+    let server = MockServer::start();
+
+    let report = json_output(&server.run(&["--dry-run", "--output", "json", "doctor"]));
+    assert_eq!(report["passed"], true);
+    assert_eq!(report["checks"][2]["status"], "skip");
+    assert!(server.state().requests.is_empty());
+}
+#[test]
+fn doctor_passes_against_a_healthy_server() {
+    let server = MockServer::start();
+
+    let report = json_output(&server.run(&["--output", "json", "doctor"]));
+    assert_eq!(report["passed"], true);
+    let checks = report["checks"].as_array().unwrap();
+    assert_eq!(checks.len(), 6);
+    assert!(checks.iter().all(|c| c["status"] == "pass"), "{}", report);
+}
+
+#[test]
+fn doctor_reports_bad_key_and_clock_skew() {
+    let server = MockServer::start();
+    server.state().clock_offset = -600;
+
+    // Valid base64, but not the key of the user
+    server.write_config("b3RoZXIta2V5", "");
+    let output = server.run(&["doctor"]);
+    assert_eq!(output.status.code(), Some(6));
+    let text = stdout(&output);
+    assert!(text.contains("[FAIL] authentication"), "{}", text);
+    assert!(text.contains("[FAIL] clock"), "{}", text);
+    assert!(text.contains("600s ahead"), "{}", text);
+    assert!(text.contains("hint:"), "{}", text);
+
+    // Not base64: the signed request is skipped
+    server.write_config("not base64!", "");
+    let text = stdout(&server.run(&["doctor"]));
+    assert!(text.contains("[FAIL] secret key"), "{}", text);
+    assert!(text.contains("[SKIP] authentication"), "{}", text);
+    assert!(text.contains("[PASS] websocket"), "{}", text);
+
+    // The key is checked as it is signed, whitespace included
+    server.write_config(&format!("{} ", support::SECRET_KEY), "");
+    let output = server.run(&["doctor"]);
+    assert_eq!(output.status.code(), Some(6));
+    let text = stdout(&output);
+    assert!(text.contains("Secret key has leading or trailing whitespace"), "{}", text);
+}
+
+#[test]
+fn doctor_reports_unreachable_host() {
+    let server = MockServer::start();
+    let port = {
+        // A port with no server behind it
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().port()
+    };
+    std::fs::write(
+        server.config_path(),
+        format!("host = \"127.0.0.1\"\nport = {}\nuser = \"c1\"\nsecretkey = \"{}\"\n", port, support::SECRET_KEY),
+    )
+    .unwrap();
+
+    let output = server.run(&["doctor"]);
+    assert_eq!(output.status.code(), Some(8));
+    let text = stdout(&output);
+    assert!(text.contains("[FAIL] host reachable"), "{}", text);
+    assert!(text.contains("[SKIP] websocket"), "{}", text);
+}
+
+#[test]
+fn pipeline_runs_steps_with_references() {
+    let server = MockServer::start();
+    server.state().job_polls = 2;
+    let data = temp_file("pipeline-iris.csv", IRIS);
+    let out = std::env::temp_dir().join(format!("sym-rest-rc-{}-pipeline-out.csv", std::process::id()));
+    let pipeline = temp_file(
+        "pipeline-ok.toml",
+        &format!(
+            r#"
+[vars]
+project = "p1"
+
+[[steps]]
+id = "create"
+command = "project create"
+name = "${{vars.project}}"
+type = "cpu"
+
+[[steps]]
+command = "learn"
+project = "${{create.project}}"
+file = "{data}"
+
+[[steps]]
+id = "build"
+command = "model build"
+project = "${{create.project}}"
+name = "m1"
+type = "hba"
+target_names = ["species"]
+input_names = ["sepal_length", "sepal_width"]
+
+[[steps]]
+command = "model predict"
+project = "${{build.project}}"
+model = "${{build.model}}"
+file = "{data}"
+out = "{out}"
+"#,
+            data = data.display(),
+            out = out.display()
+        ),
+    );
+
+    let result = json_output(&server.run(&["--output", "json", "run", pipeline.to_str().unwrap()]));
+    assert_eq!(result["passed"], true);
+    assert_eq!(result["steps"].as_array().unwrap().len(), 4);
+    // The build job was waited on before predicting with the model
+    assert_eq!(result["results"]["build"]["job"]["state"], "DONE", "{}", result);
+    assert_eq!(server.state().projects["p1"].rows, 5);
+    assert_eq!(std::fs::read_to_string(&out).unwrap().lines().count(), 6);
+}
+
+#[test]
+fn pipeline_stops_at_first_failure() {
+    let server = MockServer::start();
+    let pipeline = temp_file(
+        "pipeline-fail.toml",
+        r#"
+[[steps]]
+id = "info"
+command = "project info"
+project = "missing"
+
+[[steps]]
+command = "project delete"
+project = "${info.project}"
+"#,
+    );
+
+    let output = server.run(&["run", pipeline.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains("FAILED   info"), "{}", text);
+    assert!(text.contains("SKIPPED  step2"), "{}", text);
+    assert!(server.state().requests.iter().all(|r| r.method != "DELETE"));
+}
+
+#[test]
+fn pipeline_predict_step_needs_an_output_file() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    let data = temp_file("pipeline-predict-data.csv", IRIS);
+    let pipeline = temp_file(
+        "pipeline-predict.toml",
+        &format!(
+            r#"
+[[steps]]
+command = "project info"
+project = "p1"
+
+[[steps]]
+id = "score"
+command = "model predict"
+project = "p1"
+model = "m1"
+file = "{}"
+"#,
+            data.display()
+        ),
+    );
+
+    // The pipeline is rejected before any step runs
+    let output = server.run(&["run", pipeline.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Step 'score': model predict needs out"), "{}", stderr(&output));
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn pipeline_steps_cannot_set_global_flags() {
+    let server = MockServer::start();
+    server.add_project("p1");
+
+    for (flag, value) in [("profile", "\"prod\""), ("output", "\"yaml\""), ("port", "1"), ("dry_run", "true")] {
+        let pipeline = temp_file(
+            &format!("pipeline-global-{}.toml", flag),
+            &format!("[[steps]]\ncommand = \"project info\"\nproject = \"p1\"\n{} = {}\n", flag, value),
+        );
+        let output = server.run(&["run", pipeline.to_str().unwrap()]);
+        assert_eq!(output.status.code(), Some(2), "{}", flag);
+        let expected = format!("--{} is a global option and cannot be set in a step", flag.replace('_', "-"));
+        assert!(stdout(&output).contains(&expected), "{}", stdout(&output));
+    }
+    assert!(server.state().requests.is_empty());
+}
+
+#[test]
+fn exit_code_2_for_usage_errors() {
+    let server = MockServer::start();
+    let output = server.run(&["model", "predict", "--project", "p1", "--model", "m1"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn exit_code_3_for_a_missing_config_file() {
+    let server = MockServer::start();
+    std::fs::remove_file(server.config_path()).unwrap();
+    let output = server.run(&["project", "info", "--project", "p1"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn exit_code_4_for_a_missing_data_file() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let output = server.run(&["learn", "--project", "p1", "--file", "/nonexistent.csv"]);
+    assert_eq!(output.status.code(), Some(4));
+}
+
+#[test]
+fn exit_code_5_for_malformed_csv() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    let ragged = temp_file("exit-ragged.csv", "a,b\n1,2\n3\n");
+    let output = server.run(&["learn", "--project", "p1", "--file", ragged.to_str().unwrap(), "--types", "C,C"]);
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn exit_code_6_for_a_rejected_signature() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    // Valid base64, but not the key of the user
+    server.write_config("b3RoZXIta2V5", "");
+    let output = server.run(&["project", "info", "--project", "p1"]);
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn exit_code_7_for_http_errors() {
+    let server = MockServer::start();
+    let output = server.run(&["project", "info", "--project", "missing"]);
+    assert_eq!(output.status.code(), Some(7));
+}
+
+#[test]
+fn exit_code_7_for_an_invalid_rest_response() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    // Predictions missing a row
+    server.state().short_predictions = true;
+    let iris = temp_file("exit-invalid-rest.csv", IRIS);
+    let out = temp_file("exit-invalid-rest-out.csv", "");
+    let output = server.run(&[
+        "model", "predict", "--project", "p1", "--model", "m1", "--file", iris.to_str().unwrap(), "--out", out.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(7));
+}
+
+#[test]
+fn exit_code_8_for_network_errors() {
+    let server = MockServer::start();
+    // Nothing listening on the port
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    std::fs::write(
+        server.config_path(),
+        format!("host = \"127.0.0.1\"\nport = {}\nuser = \"c1\"\nsecretkey = \"{}\"\n", port, support::SECRET_KEY),
+    )
+    .unwrap();
+    let output = server.run(&["--retries", "0", "project", "info", "--project", "p1"]);
+    assert_eq!(output.status.code(), Some(8));
+}
+
+#[test]
+fn exit_code_9_for_a_rejected_websocket_message() {
+    let server = MockServer::start();
+    let iris = temp_file("exit-ws.csv", IRIS);
+    let output = server.run(&["learn", "--project", "missing", "--file", iris.to_str().unwrap(), "--use-ws"]);
+    assert_eq!(output.status.code(), Some(9));
+}
+
+#[test]
+fn exit_code_9_for_an_invalid_websocket_response() {
+    let server = MockServer::start();
+    server.add_model("p1", "m1", "lsvm");
+    // Predictions missing a row
+    server.state().short_predictions = true;
+    let iris = temp_file("exit-invalid-ws.csv", IRIS);
+    let out = temp_file("exit-invalid-ws-out.csv", "");
+    let output = server.run(&[
+        "model", "predict", "--project", "p1", "--model", "m1", "--file", iris.to_str().unwrap(), "--out", out.to_str().unwrap(),
+        "--use-ws",
+    ]);
+    assert_eq!(output.status.code(), Some(9));
+}
+
+#[test]
+fn exit_code_10_when_the_job_wait_times_out() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    // Job still running when the wait times out
+    server.state().job_polls = 100;
+    let output = server.run(&[
+        "model", "build", "--project", "p1", "--name", "m1", "--type", "hba", "--inputs", "0", "--targets", "1",
+        "--wait", "--wait-timeout", "0",
+    ]);
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[test]
+fn exit_code_10_for_a_failed_job() {
+    let server = MockServer::start();
+    server.add_project("p1");
+    // Job reported as failed by the server
+    server.state().job_polls = 0;
+    server.state().failing_jobs = true;
+    let output = server.run(&[
+        "model", "build", "--project", "p1", "--name", "m1", "--type", "hba", "--inputs", "0", "--targets", "1", "--wait",
+    ]);
+    assert_eq!(output.status.code(), Some(10));
+}
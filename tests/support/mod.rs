@@ -0,0 +1,789 @@
+//! Local mock of the SymetryML server for end-to-end tests
+//!
+//! Implements the REST endpoints used by the client and the `/symetry/ws/learn`
+//! and `/symetry/ws/predict` WebSocket endpoints. Every request must carry a valid
+//! HMAC signature; the string to sign is rebuilt here independently of the client
+//! code so the tests catch any change in the signing layout.
+
+#![allow(dead_code)]
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const CUSTOMER_ID: &str = "c1";
+pub const SECRET_KEY: &str = "c2VjcmV0LWtleS1mb3ItdGVzdHM=";
+
+/// Project stored by the mock
+#[derive(Debug, Default, Clone)]
+pub struct MockProject {
+    pub attribute_names: Vec<String>,
+    pub attribute_types: Vec<String>,
+    pub rows: usize,
+    pub models: BTreeMap<String, String>,
+    pub evt_wrappers: Vec<String>,
+}
+
+/// Asynchronous job created by build and autoSelect
+#[derive(Debug, Clone)]
+struct MockJob {
+    project: String,
+    /// Model and algorithm created when the job is DONE, none for a learn job
+    model: Option<(String, String)>,
+    /// Number of polls answered RUNNING before the job is DONE
+    polls_left: usize,
+}
+
+/// A request received by the mock
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub body: String,
+    pub authenticated: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub projects: BTreeMap<String, MockProject>,
+    jobs: HashMap<String, MockJob>,
+    next_job: u64,
+    pub requests: Vec<RecordedRequest>,
+    /// Number of WebSocket messages received per endpoint ("learn" or "predict")
+    pub ws_messages: HashMap<String, usize>,
+    /// Number of ACCEPTED (running) answers given for every new job
+    pub job_polls: usize,
+    /// Report jobs as failed once they stop running, instead of building the model
+    pub failing_jobs: bool,
+    /// Leave the last row out of every prediction
+    pub short_predictions: bool,
+    /// Statuses returned, in order, to the next REST requests instead of handling them
+    pub fail_next: VecDeque<u16>,
+    /// Never answer requests or WebSocket messages, to trigger client timeouts
+    pub silent: bool,
+    /// Seconds added to the time sent in the `Date` header
+    pub clock_offset: i64,
+    /// Connections are made over TLS (https:// and wss://)
+    pub tls: bool,
+}
+
+impl MockState {
+    /// Register a new job on a project and return its ID
+    fn start_job(&mut self, project: &str, model: Option<(String, String)>) -> String {
+        self.next_job += 1;
+        let job_id = self.next_job.to_string();
+        self.jobs.insert(
+            job_id.clone(),
+            MockJob {
+                project: project.to_string(),
+                model,
+                polls_left: self.job_polls,
+            },
+        );
+        job_id
+    }
+}
+
+/// Handle to a running mock server
+pub struct MockServer {
+    port: u16,
+    state: Arc<Mutex<MockState>>,
+    config_path: PathBuf,
+}
+
+impl MockServer {
+    /// Start a mock server on a free local port
+    pub fn start() -> Self {
+        Self::start_with(false)
+    }
+
+    /// Start a mock server accepting TLS connections only
+    ///
+    /// The server certificate (tests/support/tls/server.pem, for 127.0.0.1) is
+    /// signed by the second certificate of `ca_bundle_path()`.
+    pub fn start_tls() -> Self {
+        Self::start_with(true)
+    }
+
+    fn start_with(tls: bool) -> Self {
+        let state = Arc::new(Mutex::new(MockState {
+            job_polls: 1,
+            tls,
+            ..MockState::default()
+        }));
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let server_state = state.clone();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let addr = listener.local_addr().unwrap();
+                if tls {
+                    let front = TcpListener::bind("127.0.0.1:0").await.unwrap();
+                    tx.send(front.local_addr().unwrap().port()).unwrap();
+                    tokio::spawn(terminate_tls(front, addr));
+                } else {
+                    tx.send(addr.port()).unwrap();
+                }
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    tokio::spawn(handle_connection(stream, server_state.clone()));
+                }
+            });
+        });
+
+        let port = rx.recv().unwrap();
+        let config_path = std::env::temp_dir().join(format!(
+            "sym-rest-rc-mock-{}-{}.conf",
+            std::process::id(),
+            port
+        ));
+        let server = MockServer {
+            port,
+            state,
+            config_path,
+        };
+        server.write_config(SECRET_KEY, "");
+        server
+    }
+
+    /// Port the server listens on
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Shared server state
+    pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Add a project directly, without going through the API
+    pub fn add_project(&self, name: &str) {
+        self.state().projects.insert(name.to_string(), MockProject::default());
+    }
+
+    /// Add a model to a project directly, without going through the API
+    pub fn add_model(&self, project: &str, model: &str, algo: &str) {
+        self.state()
+            .projects
+            .entry(project.to_string())
+            .or_default()
+            .models
+            .insert(model.to_string(), algo.to_string());
+    }
+
+    /// Path of a configuration file pointing at this server
+    pub fn config_path(&self) -> &PathBuf {
+        &self.config_path
+    }
+
+    /// Rewrite the configuration file with a secret key and extra settings
+    /// The `secretkey` line is left out when `secret_key` is empty
+    pub fn write_config(&self, secret_key: &str, extra: &str) {
+        let secret_line = if secret_key.is_empty() {
+            String::new()
+        } else {
+            format!("secretkey = \"{}\"\n", secret_key)
+        };
+        std::fs::write(
+            &self.config_path,
+            format!(
+                "host = \"127.0.0.1\"\nport = {}\nuser = \"{}\"\n{}{}\n",
+                self.port, CUSTOMER_ID, secret_line, extra
+            ),
+        )
+        .unwrap();
+    }
+
+    /// Run the command line tool against this server
+    pub fn run(&self, args: &[&str]) -> Output {
+        command()
+            .arg("--config")
+            .arg(&self.config_path)
+            .args(args)
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.config_path);
+    }
+}
+
+/// Command running the command line tool, without the `SML_*` variables of the environment
+pub fn command() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_sym-rest-rc"));
+    for (name, _) in std::env::vars() {
+        if name.starts_with("SML_") {
+            command.env_remove(name);
+        }
+    }
+    command
+}
+
+/// Write a file in the temporary directory and return its path
+pub fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("sym-rest-rc-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Path of a PEM bundle of two CA certificates, the second of which signed the TLS server certificate
+pub fn ca_bundle_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/support/tls/ca-bundle.pem")
+}
+
+/// Accept TLS connections on `front` and forward the decrypted streams to the plain listener at `backend`
+async fn terminate_tls(front: TcpListener, backend: SocketAddr) {
+    let identity = native_tls::Identity::from_pkcs8(
+        include_bytes!("tls/server.pem"),
+        include_bytes!("tls/server.key"),
+    )
+    .unwrap();
+    let acceptor = tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+    loop {
+        let (stream, _) = front.accept().await.unwrap();
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            // A client rejecting the certificate closes the connection during the handshake
+            let Ok(mut tls_stream) = acceptor.accept(stream).await else {
+                return;
+            };
+            let Ok(mut plain) = TcpStream::connect(backend).await else {
+                return;
+            };
+            let _ = tokio::io::copy_bidirectional(&mut tls_stream, &mut plain).await;
+        });
+    }
+}
+
+/// Route a connection to the WebSocket or the HTTP handler
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    // Peek at the request head without consuming it, so a WebSocket handshake
+    // can still be read by tokio-tungstenite
+    let mut buf = vec![0u8; 8192];
+    let head = loop {
+        let n = match stream.peek(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => n,
+        };
+        let text = String::from_utf8_lossy(&buf[..n]).to_string();
+        if text.contains("\r\n\r\n") || n == buf.len() {
+            break text;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    };
+
+    if head.to_ascii_lowercase().contains("upgrade: websocket") {
+        handle_ws(stream, state).await;
+    } else {
+        handle_http(stream, state).await;
+    }
+}
+
+/// HTTP request parsed by the mock
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Option<String>,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> &str {
+        self.headers.get(&name.to_ascii_lowercase()).map(String::as_str).unwrap_or("")
+    }
+
+    fn query_param(&self, name: &str) -> Option<String> {
+        self.query.as_deref()?.split('&').find_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            (k == name).then(|| v.to_string())
+        })
+    }
+}
+
+/// Read one HTTP request
+async fn read_request(stream: &mut TcpStream) -> Option<HttpRequest> {
+    let mut data = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+    let (path, query) = match target.split_once('?') {
+        Some((p, q)) => (p.to_string(), Some(q.to_string())),
+        None => (target, None),
+    };
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = data[head_end + 4..].to_vec();
+    while body.len() < length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+
+    Some(HttpRequest {
+        method,
+        path,
+        query,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/// Compute the base64 HMAC-SHA256 of a string with the base64 secret key
+fn hmac(secret_key: &str, string_to_sign: &str) -> String {
+    let key = BASE64.decode(secret_key).unwrap();
+    let mut mac = HmacSha256::new_from_slice(&key).unwrap();
+    mac.update(string_to_sign.as_bytes());
+    BASE64.encode(mac.finalize().into_bytes())
+}
+
+/// Check the signature of a REST request
+///
+/// The string to sign is `method\nmd5\nsecret\ndate\ncustomer\n[body\n]resource\n[query\n]`.
+fn verify_rest(request: &HttpRequest) -> Result<(), String> {
+    let customer_id = request.header("Customer-ID");
+    if customer_id != CUSTOMER_ID {
+        return Err(format!("Unknown customer '{}'", customer_id));
+    }
+    if request.header("sym-version").is_empty() {
+        return Err("Missing sym-version header".to_string());
+    }
+
+    let content_md5 = request.header("Content-MD5");
+    let has_body = !request.body.is_empty();
+    if has_body && content_md5 != BASE64.encode(md5::compute(request.body.as_bytes()).0) {
+        return Err("Content-MD5 does not match the body".to_string());
+    }
+
+    let mut string_to_sign = format!(
+        "{}\n{}\n{}\n{}\n{}\n",
+        request.method,
+        content_md5,
+        SECRET_KEY,
+        request.header("Sym-date"),
+        customer_id
+    );
+    if has_body {
+        string_to_sign.push_str(&request.body);
+        string_to_sign.push('\n');
+    }
+    string_to_sign.push_str(&request.path);
+    string_to_sign.push('\n');
+    if let Some(q) = &request.query {
+        string_to_sign.push_str(q);
+        string_to_sign.push('\n');
+    }
+
+    if request.header("Authorization") != hmac(SECRET_KEY, &string_to_sign) {
+        return Err("Invalid signature".to_string());
+    }
+    Ok(())
+}
+
+/// Server envelope
+fn envelope(status_code: &str, status_string: &str, values: Value) -> Value {
+    json!({
+        "statusCode": status_code,
+        "statusString": status_string,
+        "values": values,
+    })
+}
+
+/// HTTP response produced by a route
+struct HttpResponse {
+    status: u16,
+    job_id: Option<String>,
+    body: Value,
+}
+
+impl HttpResponse {
+    fn ok(body: Value) -> Self {
+        HttpResponse { status: 200, job_id: None, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        HttpResponse {
+            status,
+            job_id: None,
+            body: envelope("ERROR", message, json!({})),
+        }
+    }
+}
+
+async fn handle_http(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    while let Some(request) = read_request(&mut stream).await {
+        let auth = verify_rest(&request);
+
+        state.lock().unwrap().requests.push(RecordedRequest {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            query: request.query.clone(),
+            body: request.body.clone(),
+            authenticated: auth.is_ok(),
+        });
+
+        if state.lock().unwrap().silent {
+            return std::future::pending().await;
+        }
+        let injected = state.lock().unwrap().fail_next.pop_front();
+        let response = match (injected, auth) {
+            (Some(status), _) => HttpResponse::error(status, "Injected failure"),
+            (None, Ok(())) => route(&request, &mut state.lock().unwrap()),
+            (None, Err(e)) => HttpResponse::error(401, &e),
+        };
+
+        let body = response.body.to_string();
+        let reason = match response.status {
+            200 => "OK",
+            202 => "Accepted",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            503 => "Service Unavailable",
+            _ => "Error",
+        };
+        let mut out = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+            response.status,
+            reason,
+            body.len()
+        );
+        let date = chrono::Utc::now() + chrono::Duration::seconds(state.lock().unwrap().clock_offset);
+        out.push_str(&format!("Date: {}\r\n", date.format("%a, %d %b %Y %H:%M:%S GMT")));
+        if let Some(job_id) = &response.job_id {
+            out.push_str(&format!("sym-job-id: {}\r\n", job_id));
+        }
+        out.push_str("\r\n");
+        out.push_str(&body);
+        if stream.write_all(out.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Dispatch a REST request on `/symetry/rest/{cid}/...`
+fn route(request: &HttpRequest, state: &mut MockState) -> HttpResponse {
+    let prefix = format!("/symetry/rest/{}/", CUSTOMER_ID);
+    let Some(rest) = request.path.strip_prefix(&prefix) else {
+        return HttpResponse::error(404, "Unknown resource");
+    };
+    let parts: Vec<&str> = rest.split('/').collect();
+    let body: Value = serde_json::from_str(&request.body).unwrap_or(Value::Null);
+
+    match (request.method.as_str(), parts.as_slice()) {
+        ("POST", ["projects"]) => {
+            let Some(pid) = request.query_param("pid") else {
+                return HttpResponse::error(400, "Missing pid");
+            };
+            if state.projects.contains_key(&pid) {
+                return HttpResponse::error(400, "Project already exists");
+            }
+            state.projects.insert(pid, MockProject::default());
+            HttpResponse::ok(envelope("OK", "Project created", json!({})))
+        }
+        ("GET", ["projects"]) => HttpResponse::ok(envelope(
+            "OK",
+            "",
+            json!({ "projects": state.projects.keys().collect::<Vec<_>>() }),
+        )),
+        ("DELETE", ["projects", pid]) => match state.projects.remove(*pid) {
+            Some(_) => HttpResponse::ok(envelope("OK", "Project deleted", json!({}))),
+            None => HttpResponse::error(404, "Unknown project"),
+        },
+        ("GET", ["projects", pid, "info"]) => match state.projects.get(*pid) {
+            Some(p) => HttpResponse::ok(envelope(
+                "OK",
+                "",
+                json!({
+                    "info": json!({
+                        "name": pid,
+                        "attributeNames": p.attribute_names,
+                        "attributeTypes": p.attribute_types,
+                        "numRows": p.rows,
+                        "models": p.models.keys().collect::<Vec<_>>(),
+                    }).to_string()
+                }),
+            )),
+            None => HttpResponse::error(404, "Unknown project"),
+        },
+        ("POST", ["projects", pid, "learn"]) => {
+            let Some(project) = state.projects.get_mut(*pid) else {
+                return HttpResponse::error(404, "Unknown project");
+            };
+            let rows = learn(project, &body);
+            let job_id = state.start_job(pid, None);
+            HttpResponse {
+                status: 202,
+                job_id: Some(job_id),
+                body: envelope("OK", &format!("Learned {} rows", rows), json!({})),
+            }
+        }
+        ("POST", ["projects", pid, op @ ("build" | "autoSelect")]) => {
+            if !state.projects.contains_key(*pid) {
+                return HttpResponse::error(404, "Unknown project");
+            }
+            let Some(model) = request.query_param("modelid") else {
+                return HttpResponse::error(400, "Missing modelid");
+            };
+            let algo = match *op {
+                "build" => request.query_param("algo").unwrap_or_default(),
+                _ => "auto".to_string(),
+            };
+            let job_id = state.start_job(pid, Some((model, algo)));
+            HttpResponse {
+                status: 202,
+                job_id: Some(job_id),
+                body: envelope("OK", "Job started", json!({})),
+            }
+        }
+        ("POST", ["projects", pid, "predict", model]) => {
+            let known = state
+                .projects
+                .get(*pid)
+                .map(|p| p.models.contains_key(*model))
+                .unwrap_or(false);
+            if !known {
+                return HttpResponse::error(404, "Unknown model");
+            }
+            HttpResponse::ok(envelope(
+                "OK",
+                "",
+                json!({ "dataframe": predict(model, &body, state.short_predictions).to_string() }),
+            ))
+        }
+        ("GET", ["jobs", id]) => {
+            let Some(job) = state.jobs.get_mut(*id) else {
+                return HttpResponse::error(404, "Unknown job");
+            };
+            if job.polls_left > 0 {
+                job.polls_left -= 1;
+                return HttpResponse {
+                    status: 202,
+                    job_id: None,
+                    body: envelope("ACCEPTED", "Job running", json!({})),
+                };
+            }
+            if state.failing_jobs {
+                return HttpResponse::error(500, "Job failed");
+            }
+            let job = job.clone();
+            if let (Some(project), Some((model, algo))) = (state.projects.get_mut(&job.project), job.model) {
+                project.models.insert(model, algo);
+            }
+            HttpResponse::ok(envelope("OK", "Job finished", json!({})))
+        }
+        ("GET", ["projects", pid, "models", model]) => {
+            match state.projects.get(*pid).and_then(|p| p.models.get(*model)) {
+                Some(algo) => HttpResponse::ok(envelope(
+                    "OK",
+                    "",
+                    json!({ "modelInfo": { "modelId": model, "algo": algo } }),
+                )),
+                None => HttpResponse::error(404, "Unknown model"),
+            }
+        }
+        ("DELETE", ["projects", pid, "models", model]) => {
+            match state.projects.get_mut(*pid).and_then(|p| p.models.remove(*model)) {
+                Some(_) => HttpResponse::ok(envelope("OK", "Model deleted", json!({}))),
+                None => HttpResponse::error(404, "Unknown model"),
+            }
+        }
+        (method @ ("POST" | "DELETE"), ["projects", pid, model, "evtwrapper"]) => {
+            let Some(project) = state.projects.get_mut(*pid) else {
+                return HttpResponse::error(404, "Unknown project");
+            };
+            if method == "POST" {
+                project.evt_wrappers.push(model.to_string());
+            } else {
+                project.evt_wrappers.retain(|m| m != model);
+            }
+            HttpResponse::ok(envelope("OK", "", json!({})))
+        }
+        _ => HttpResponse::error(404, "Unknown resource"),
+    }
+}
+
+/// Add the rows of a DataFrame to a project and return the number of rows
+fn learn(project: &mut MockProject, dataframe: &Value) -> usize {
+    let strings = |key: &str| -> Vec<String> {
+        dataframe[key]
+            .as_array()
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    project.attribute_names = strings("attributeNames");
+    project.attribute_types = strings("attributeTypes");
+    let rows = dataframe["data"].as_array().map(|d| d.len()).unwrap_or(0);
+    project.rows += rows;
+    rows
+}
+
+/// Predictions for a DataFrame: one row per input row, echoing the first value
+/// so the tests can check the row order. `short` leaves the last row out.
+fn predict(model: &str, dataframe: &Value, short: bool) -> Value {
+    let mut data: Vec<Value> = dataframe["data"]
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .map(|row| json!([format!("pred-{}", row[0].as_str().unwrap_or(""))]))
+                .collect()
+        })
+        .unwrap_or_default();
+    if short {
+        data.pop();
+    }
+    json!({
+        "attributeNames": [format!("{}_prediction", model)],
+        "data": data,
+    })
+}
+
+/// Check the signature of a WebSocket message and return the DataFrame and extra keys
+///
+/// The string to sign is `md5\nsecret\ndate\ncustomer\npayload\nurl\nextra_key...\n`.
+fn verify_ws(message: &str, url: &str) -> Result<(Value, Vec<String>), String> {
+    let (length, rest) = message.split_once(',').ok_or("Missing length prefix")?;
+    let length: usize = length.parse().map_err(|_| "Invalid length prefix")?;
+    if rest.len() < length {
+        return Err("Length prefix exceeds the message".to_string());
+    }
+    let (headers_json, payload) = rest.split_at(length);
+
+    let headers: Value = serde_json::from_str(headers_json).map_err(|e| e.to_string())?;
+    let fields: Vec<&str> = headers["headers"]
+        .as_array()
+        .map(|a| a.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let [date, md5, authorization, customer_id] = fields.as_slice() else {
+        return Err("Expected four headers".to_string());
+    };
+    let extra_keys: Vec<String> = headers["extraKeys"]
+        .as_array()
+        .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+
+    if *customer_id != CUSTOMER_ID {
+        return Err(format!("Unknown customer '{}'", customer_id));
+    }
+    if *md5 != BASE64.encode(md5::compute(payload.as_bytes()).0) {
+        return Err("MD5 does not match the payload".to_string());
+    }
+
+    let mut string_to_sign = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n",
+        md5, SECRET_KEY, date, customer_id, payload, url
+    );
+    for key in &extra_keys {
+        string_to_sign.push_str(key);
+        string_to_sign.push('\n');
+    }
+    if *authorization != hmac(SECRET_KEY, &string_to_sign) {
+        return Err("Invalid signature".to_string());
+    }
+
+    let dataframe = serde_json::from_str(payload).map_err(|e| e.to_string())?;
+    Ok((dataframe, extra_keys))
+}
+
+async fn handle_ws(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    // The client signs the full URL, rebuilt here from the Host header and path
+    let url = Arc::new(Mutex::new(String::new()));
+    let handshake_url = url.clone();
+    let scheme = if state.lock().unwrap().tls { "wss" } else { "ws" };
+    // The callback signature is fixed by tungstenite
+    #[allow(clippy::result_large_err)]
+    let callback = move |request: &Request, response: Response| {
+        let host = request
+            .headers()
+            .get("host")
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("");
+        *handshake_url.lock().unwrap() = format!("{}://{}{}", scheme, host, request.uri().path());
+        Ok(response)
+    };
+    let Ok(ws) = tokio_tungstenite::accept_hdr_async(stream, callback).await else {
+        return;
+    };
+    let url = url.lock().unwrap().clone();
+    let endpoint = url.rsplit('/').next().unwrap_or("").to_string();
+
+    let (mut write, mut read) = ws.split();
+    while let Some(Ok(message)) = read.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        if state.lock().unwrap().silent {
+            continue;
+        }
+
+        let reply = match verify_ws(&text, &url) {
+            Err(e) => envelope("ERROR", &e, json!({})),
+            Ok((dataframe, extra_keys)) => {
+                let mut state = state.lock().unwrap();
+                *state.ws_messages.entry(endpoint.clone()).or_default() += 1;
+                match (endpoint.as_str(), extra_keys.as_slice()) {
+                    ("learn", [pid]) => match state.projects.get_mut(pid) {
+                        Some(project) => {
+                            let rows = learn(project, &dataframe);
+                            envelope("OK", &format!("Learned {} rows", rows), json!({}))
+                        }
+                        None => envelope("ERROR", "Unknown project", json!({})),
+                    },
+                    ("predict", [_, model]) => envelope(
+                        "OK",
+                        "",
+                        json!({ "dataframe": predict(model, &dataframe, state.short_predictions).to_string() }),
+                    ),
+                    _ => envelope("ERROR", "Unexpected extra keys", json!({})),
+                }
+            }
+        };
+
+        if write.send(Message::Text(reply.to_string())).await.is_err() {
+            return;
+        }
+    }
+}